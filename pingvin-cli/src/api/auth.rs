@@ -0,0 +1,109 @@
+use super::{LoginResult, PingvinApi};
+
+/// Strategy to authenticate the requests of the API client
+pub trait Authenticator {
+    async fn authenticate(&self, server_api: &mut PingvinApi) -> anyhow::Result<LoginResult>;
+}
+
+/// Sign in with the username and password of the user.
+/// Users with two-factor authentication need to complete the login using [`PingvinApi::login_totp`].
+pub struct PasswordAuthenticator {
+    pub username: String,
+    pub password: String,
+}
+
+impl Authenticator for PasswordAuthenticator {
+    async fn authenticate(&self, server_api: &mut PingvinApi) -> anyhow::Result<LoginResult> {
+        server_api.login(&self.username, &self.password).await
+    }
+}
+
+/// Sign in with a refresh token instead of a password, which is exchanged for access tokens.
+/// The token is the `refresh_token` cookie of a login session, e.g. of the web interface.
+/// It stays valid for the session duration configured on the server (3 months by default),
+/// unlike the access tokens which expire after a few minutes.
+/// Works for accounts which can not sign in with a password, e.g. users of OAuth providers.
+pub struct TokenAuthenticator {
+    pub token: String,
+}
+
+impl Authenticator for TokenAuthenticator {
+    async fn authenticate(&self, server_api: &mut PingvinApi) -> anyhow::Result<LoginResult> {
+        server_api.login_refresh_token(&self.token).await
+    }
+}
+
+impl PingvinApi {
+    pub async fn authenticate(
+        &mut self,
+        authenticator: &impl Authenticator,
+    ) -> anyhow::Result<LoginResult> {
+        authenticator.authenticate(self).await
+    }
+
+    /// Whether the requests are sent on behalf of a user
+    pub fn is_authenticated(&self) -> bool {
+        self.session.is_some()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PasswordAuthenticator, TokenAuthenticator};
+    use crate::api::{mock::MockServer, LoginResult, PingvinApi};
+
+    #[tokio::test]
+    async fn test_authenticators() {
+        let server = MockServer::start(16).await;
+        server
+            .state()
+            .users
+            .insert("alice".to_string(), "secret".to_string());
+        server.state().refresh_tokens.push("ci-token".to_string());
+
+        let mut api = PingvinApi::new(server.base_url()).unwrap();
+        let result = api
+            .authenticate(&PasswordAuthenticator {
+                username: "alice".to_string(),
+                password: "wrong".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result, LoginResult::Rejected);
+        assert!(!api.is_authenticated());
+
+        let result = api
+            .authenticate(&PasswordAuthenticator {
+                username: "alice".to_string(),
+                password: "secret".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result, LoginResult::LoggedIn);
+        assert!(api.is_authenticated());
+
+        let mut api = PingvinApi::new(server.base_url()).unwrap();
+        let result = api
+            .authenticate(&TokenAuthenticator {
+                token: "invalid".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result, LoginResult::Rejected);
+        assert!(!api.is_authenticated());
+
+        let result = api
+            .authenticate(&TokenAuthenticator {
+                token: "ci-token".to_string(),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result, LoginResult::LoggedIn);
+        assert!(api.is_authenticated());
+        assert_eq!(
+            api.session().unwrap().refresh_token.as_deref(),
+            Some("ci-token")
+        );
+        assert!(api.list_shares().await.unwrap().is_empty());
+    }
+}
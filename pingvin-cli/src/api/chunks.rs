@@ -0,0 +1,243 @@
+use std::io::SeekFrom;
+
+use anyhow::Context;
+use bytes::{Bytes, BytesMut};
+use futures::{stream::BoxStream, StreamExt};
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncReadExt, AsyncSeekExt},
+};
+use tokio_util::codec::{BytesCodec, FramedRead};
+
+/// Size of the blocks a buffered chunk will be sent in, so the upload progress can be tracked
+const BODY_BLOCK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub struct Chunk {
+    pub index: usize,
+
+    /// Total amount of chunks as reported to the server.
+    /// For streams this is only known once the end of the stream has been reached.
+    pub total: usize,
+
+    pub offset: usize,
+    pub length: usize,
+
+    data: Option<Bytes>,
+}
+
+/// Source of the chunks uploaded for a single file
+pub enum ChunkSource<'a> {
+    /// Chunks will be read on demand from a file with a known length
+    File {
+        file: File,
+        length: usize,
+        chunk_size: usize,
+    },
+
+    /// Chunks will be buffered from a stream of unknown length.
+    /// The following chunk is always read ahead to detect the end of the stream,
+    /// so the total chunk count can be fixed when uploading the last chunk.
+    Stream {
+        reader: &'a mut (dyn AsyncRead + Unpin),
+        chunk_size: usize,
+
+        index: Option<usize>,
+        current: Bytes,
+        next: Bytes,
+    },
+}
+
+impl<'a> ChunkSource<'a> {
+    pub fn file(file: File, length: usize, chunk_size: usize) -> Self {
+        Self::File {
+            file,
+            length,
+            chunk_size,
+        }
+    }
+
+    pub fn stream(reader: &'a mut (dyn AsyncRead + Unpin), chunk_size: usize) -> Self {
+        Self::Stream {
+            reader,
+            chunk_size,
+            index: None,
+            current: Bytes::new(),
+            next: Bytes::new(),
+        }
+    }
+
+    /// The total length if known
+    pub fn length(&self) -> Option<usize> {
+        match self {
+            Self::File { length, .. } => Some(*length),
+            Self::Stream { .. } => None,
+        }
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        match self {
+            Self::File { chunk_size, .. } | Self::Stream { chunk_size, .. } => *chunk_size,
+        }
+    }
+
+    pub async fn chunk(&mut self, chunk_index: usize) -> anyhow::Result<Chunk> {
+        match self {
+            Self::File {
+                length, chunk_size, ..
+            } => {
+                let (length, chunk_size) = (*length, *chunk_size);
+                let total = length.div_ceil(chunk_size).max(1);
+                if chunk_index >= total {
+                    anyhow::bail!("chunk {} is out of range ({} chunks)", chunk_index, total);
+                }
+
+                let offset = chunk_index * chunk_size;
+                Ok(Chunk {
+                    index: chunk_index,
+                    total,
+                    offset,
+                    length: length.saturating_sub(offset).min(chunk_size),
+                    data: None,
+                })
+            }
+            Self::Stream {
+                reader,
+                chunk_size,
+                index,
+                current,
+                next,
+            } => {
+                match *index {
+                    Some(index) if index == chunk_index => {}
+                    Some(previous) if previous + 1 == chunk_index && !next.is_empty() => {
+                        *current = std::mem::take(next);
+                        *next = read_chunk(*reader, *chunk_size).await?;
+                    }
+                    None if chunk_index == 0 => {
+                        *current = read_chunk(*reader, *chunk_size).await?;
+                        *next = if current.len() < *chunk_size {
+                            Bytes::new()
+                        } else {
+                            read_chunk(*reader, *chunk_size).await?
+                        };
+                    }
+                    _ => anyhow::bail!(
+                        "chunk {} is not available as streams can only be uploaded sequentially",
+                        chunk_index
+                    ),
+                }
+                *index = Some(chunk_index);
+
+                let total = if next.is_empty() {
+                    chunk_index + 1
+                } else {
+                    chunk_index + 2
+                };
+                Ok(Chunk {
+                    index: chunk_index,
+                    total,
+                    offset: chunk_index * *chunk_size,
+                    length: current.len(),
+                    data: Some(current.clone()),
+                })
+            }
+        }
+    }
+
+    /// Create the request body for the given chunk.
+    /// The body can be created multiple times in case the upload needs to be retried.
+    pub async fn body(
+        &self,
+        chunk: &Chunk,
+    ) -> anyhow::Result<BoxStream<'static, std::io::Result<Bytes>>> {
+        if let Some(data) = &chunk.data {
+            let blocks = (0..data.len())
+                .step_by(BODY_BLOCK_SIZE)
+                .map({
+                    let data = data.clone();
+                    move |offset| Ok(data.slice(offset..(offset + BODY_BLOCK_SIZE).min(data.len())))
+                })
+                .collect::<Vec<_>>();
+
+            return Ok(futures::stream::iter(blocks).boxed());
+        }
+
+        let Self::File { file, .. } = self else {
+            anyhow::bail!("missing chunk data");
+        };
+
+        let mut file = file.try_clone().await?;
+        file.seek(SeekFrom::Start(chunk.offset as u64))
+            .await
+            .with_context(|| format!("seek to chunk {}", chunk.index))?;
+
+        Ok(
+            FramedRead::new(file.take(chunk.length as u64), BytesCodec::new())
+                .map(|chunk| chunk.map(BytesMut::freeze))
+                .boxed(),
+        )
+    }
+}
+
+/// Read until the chunk has been filled or the end of the stream has been reached
+async fn read_chunk(
+    reader: &mut (dyn AsyncRead + Unpin),
+    chunk_size: usize,
+) -> anyhow::Result<Bytes> {
+    let mut buffer = BytesMut::with_capacity(chunk_size);
+    while buffer.len() < chunk_size {
+        let mut limited = (&mut *reader).take((chunk_size - buffer.len()) as u64);
+        if limited.read_buf(&mut buffer).await.context("read stream")? == 0 {
+            break;
+        }
+    }
+
+    Ok(buffer.freeze())
+}
+
+#[cfg(test)]
+mod test {
+    use super::ChunkSource;
+
+    #[tokio::test]
+    async fn test_stream_chunks() {
+        let data = (0..40u8).collect::<Vec<_>>();
+        let mut reader = data.as_slice();
+        let mut source = ChunkSource::stream(&mut reader, 16);
+
+        let chunk = source.chunk(0).await.unwrap();
+        assert_eq!((chunk.total, chunk.length), (2, 16));
+
+        /* requesting the same chunk again is required for retries */
+        let chunk = source.chunk(0).await.unwrap();
+        assert_eq!((chunk.total, chunk.length), (2, 16));
+
+        let chunk = source.chunk(1).await.unwrap();
+        assert_eq!((chunk.total, chunk.length), (3, 16));
+
+        let chunk = source.chunk(2).await.unwrap();
+        assert_eq!((chunk.total, chunk.offset, chunk.length), (3, 32, 8));
+        assert!(source.chunk(3).await.is_err());
+        assert!(source.chunk(0).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stream_exact_chunks() {
+        let data = vec![1u8; 32];
+        let mut reader = data.as_slice();
+        let mut source = ChunkSource::stream(&mut reader, 16);
+
+        assert_eq!(source.chunk(0).await.unwrap().total, 2);
+        assert_eq!(source.chunk(1).await.unwrap().total, 2);
+    }
+
+    #[tokio::test]
+    async fn test_empty_stream() {
+        let mut reader: &[u8] = &[];
+        let mut source = ChunkSource::stream(&mut reader, 16);
+
+        let chunk = source.chunk(0).await.unwrap();
+        assert_eq!((chunk.total, chunk.length), (1, 0));
+    }
+}
@@ -1,5 +1,7 @@
 use dto::{ConfigEntry, ConfigValue};
+use reqwest::Url;
 use serde_json::Number;
+use std::time::Duration;
 
 pub struct PublicConfiguration {
     entries: Vec<ConfigEntry>,
@@ -43,6 +45,66 @@ impl PublicConfiguration {
             })
             .flatten()
     }
+
+    /// Base URL of the web interface
+    pub fn app_url(&self) -> &str {
+        self.get_string("general.appUrl").unwrap_or("")
+    }
+
+    /// Maximum expiration of shares or None if shares may never expire.
+    /// The server stores the maximum in hours, where zero disables the limit.
+    pub fn max_expiration(&self) -> Option<Duration> {
+        let hours = self.get_number("share.maxExpiration")?.as_u64()?;
+        if hours == 0 {
+            return None;
+        }
+
+        Some(Duration::from_secs(hours * 60 * 60))
+    }
+}
+
+/// Link to the page of the share within the web interface
+pub fn share_url(app_url: &str, share_id: &str) -> String {
+    format!("{}/s/{}", app_url, share_id)
+}
+
+/// Direct download link of a file of the share
+pub fn file_url(app_url: &str, share_id: &str, file_id: &str) -> String {
+    format!("{}/api/shares/{}/files/{}", app_url, share_id, file_id)
+}
+
+/// Download link of all files of the share as zip archive
+pub fn zip_url(app_url: &str, share_id: &str) -> String {
+    format!("{}/api/shares/{}/files/zip", app_url, share_id)
+}
+
+/// Split a link to a page of the web interface (e.g. `https://host/s/<id>`) into the API URL
+/// of the server and the id the link refers to.
+/// Returns None if the URL does not point to one of the given pages.
+pub fn parse_frontend_url(url: &Url, pages: &[&str]) -> Option<(Url, String)> {
+    let segments = url
+        .path_segments()?
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+
+    let [prefix @ .., page, id] = segments.as_slice() else {
+        return None;
+    };
+    if !pages.contains(page) {
+        return None;
+    }
+
+    let mut server_url = url.clone();
+    server_url.set_query(None);
+    server_url.set_fragment(None);
+    server_url.set_path(&format!(
+        "{}/api/",
+        prefix
+            .iter()
+            .map(|segment| format!("/{}", segment))
+            .collect::<String>()
+    ));
+    Some((server_url, id.to_string()))
 }
 
 mod dto {
@@ -92,12 +154,34 @@ mod dto {
 
     #[cfg(test)]
     mod test {
-        use crate::api::{ConfigEntry, ConfigValue};
+        use std::time::Duration;
+
+        use super::super::PublicConfiguration;
+        use super::ConfigEntry;
 
         #[test]
         fn test_parse() {
             const PAYLOAD: &str = r#"[{"key":"smtp.enabled","value":"true","type":"boolean"},{"key":"general.appName","value":"Sendy","type":"string"},{"key":"general.appUrl","value":"https://sendy.did.science","type":"string"},{"key":"general.showHomePage","value":"false","type":"boolean"},{"key":"general.sessionDuration","value":"2160","type":"number"},{"key":"share.allowRegistration","value":"false","type":"boolean"},{"key":"share.allowUnauthenticatedShares","value":"false","type":"boolean"},{"key":"share.maxExpiration","value":"0","type":"number"},{"key":"share.maxSize","value":"1000000000","type":"number"},{"key":"share.chunkSize","value":"10000000","type":"number"},{"key":"share.autoOpenShareModal","value":"false","type":"boolean"},{"key":"email.enableShareEmailRecipients","value":"true","type":"boolean"},{"key":"smtp.allowUnauthorizedCertificates","value":"true","type":"boolean"},{"key":"oauth.disablePassword","value":"false","type":"boolean"}]"#;
-            assert!(serde_json::from_str(&PAYLOAD).is_ok());
+            assert!(serde_json::from_str::<Vec<ConfigEntry>>(PAYLOAD).is_ok());
+        }
+
+        #[test]
+        fn test_max_expiration() {
+            let config = |hours: &str| {
+                let payload = format!(
+                    r#"[{{"key":"share.maxExpiration","value":"{}","type":"number"}}]"#,
+                    hours
+                );
+                PublicConfiguration::new(serde_json::from_str(&payload).unwrap())
+            };
+
+            /* a maximum expiration of zero hours disables the limit */
+            assert_eq!(config("0").max_expiration(), None);
+            assert_eq!(
+                config("48").max_expiration(),
+                Some(Duration::from_secs(48 * 60 * 60))
+            );
+            assert_eq!(PublicConfiguration::new(vec![]).max_expiration(), None);
         }
     }
 }
@@ -0,0 +1,572 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::Context;
+use futures::StreamExt;
+use reqwest::{
+    header::{HeaderMap, RANGE},
+    StatusCode, Url,
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    io::AsyncWriteExt,
+};
+
+use super::{append_cookie, PingvinApi, ShareFile};
+
+/// Share as it is presented to visitors
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteShare {
+    pub id: String,
+
+    #[serde(default)]
+    pub name: Option<String>,
+
+    #[serde(default)]
+    pub description: Option<String>,
+
+    #[serde(default)]
+    pub files: Vec<ShareFile>,
+}
+
+pub enum OpenShareResult<'a> {
+    Opened(ShareDownload<'a>),
+
+    /// The share is password protected and no password has been given
+    PasswordRequired,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct DownloadProgress {
+    pub file_name: String,
+
+    pub files_total: u64,
+    pub files_downloaded: u64,
+
+    pub bytes_total: u64,
+    pub bytes_downloaded: u64,
+}
+
+#[derive(Debug, Clone)]
+pub enum DownloadEvent {
+    DownloadProgress(DownloadProgress),
+
+    FileDownloaded {
+        path: PathBuf,
+    },
+
+    /// The file already exists with the expected size and skipping existing files has been enabled
+    FileSkipped {
+        path: PathBuf,
+    },
+
+    DownloadError {
+        file: String,
+        error: Arc<anyhow::Error>,
+    },
+
+    DownloadCompleted {
+        directory: PathBuf,
+    },
+}
+
+pub type DownloadEventCallback = dyn Fn(DownloadEvent) + 'static;
+
+#[derive(Debug, Default, Deserialize)]
+struct ErrorResponse {
+    #[serde(default)]
+    message: String,
+
+    #[serde(default)]
+    error: Option<String>,
+}
+
+/// Authentication headers including the share token cookie
+fn share_headers(
+    api: &PingvinApi,
+    share_id: &str,
+    token: Option<&str>,
+) -> anyhow::Result<HeaderMap> {
+    let mut headers = api.authentication_headers.clone();
+    if let Some(token) = token {
+        append_cookie(&mut headers, &format!("share_{}_token", share_id), token)?;
+    }
+    Ok(headers)
+}
+
+/// Path the file will be written to while it is being downloaded
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    path.with_file_name(name)
+}
+
+/// Resolve the file name within the share to a path within the target directory.
+/// Names may contain folders separated by '/', but must never escape the target directory.
+pub fn target_path(directory: &Path, name: &str) -> anyhow::Result<PathBuf> {
+    let mut path = directory.to_path_buf();
+    for component in name.split('/') {
+        if component.is_empty()
+            || component == "."
+            || component == ".."
+            || component.contains(['\\', ':'])
+        {
+            anyhow::bail!(
+                "refusing to write the file '{}' outside the target directory",
+                name
+            );
+        }
+        path.push(component);
+    }
+    Ok(path)
+}
+
+impl PingvinApi {
+    /// Open the share as a visitor.
+    /// Password protected shares require the password to obtain a share token first.
+    pub async fn open_share(
+        &self,
+        share_id: &str,
+        password: Option<&str>,
+    ) -> anyhow::Result<OpenShareResult<'_>> {
+        let url = self.base_url.join(&format!("shares/{}", share_id))?;
+
+        let mut token = None;
+        loop {
+            let response = self
+                .http_client
+                .get(url.clone())
+                .headers(share_headers(self, share_id, token.as_deref())?)
+                .send()
+                .await?;
+
+            match response.status() {
+                StatusCode::NOT_FOUND => anyhow::bail!("The share {} does not exist", share_id),
+                StatusCode::FORBIDDEN if token.is_none() => {
+                    let error = response.json::<ErrorResponse>().await.unwrap_or_default();
+                    match error.error.as_deref() {
+                        Some("share_password_required") if password.is_none() => {
+                            return Ok(OpenShareResult::PasswordRequired)
+                        }
+                        Some("share_password_required" | "share_token_required") => {
+                            token = Some(self.share_token(share_id, password).await?);
+                        }
+                        _ => anyhow::bail!(
+                            "Access to the share {} has been denied: {}",
+                            share_id,
+                            error.message
+                        ),
+                    }
+                }
+                _ => {
+                    let share = response.error_for_status()?.json::<RemoteShare>().await?;
+                    return Ok(OpenShareResult::Opened(ShareDownload {
+                        api: self,
+                        share,
+                        token,
+                        event_callback: Box::new(|_| {}),
+                        skip_existing: false,
+                    }));
+                }
+            }
+        }
+    }
+
+    /// Obtain the token granting access to the share and its files
+    async fn share_token(&self, share_id: &str, password: Option<&str>) -> anyhow::Result<String> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            #[serde(skip_serializing_if = "Option::is_none")]
+            password: Option<&'a str>,
+        }
+
+        #[derive(Deserialize)]
+        struct Response {
+            token: String,
+        }
+
+        let response = self
+            .http_client
+            .post(self.base_url.join(&format!("shares/{}/token", share_id))?)
+            .json(&Request { password })
+            .headers(self.authentication_headers.clone())
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::FORBIDDEN {
+            let error = response.json::<ErrorResponse>().await.unwrap_or_default();
+            anyhow::bail!(
+                "Access to the share {} has been denied: {}",
+                share_id,
+                error.message
+            );
+        }
+
+        let response = response.error_for_status()?.json::<Response>().await?;
+        Ok(response.token)
+    }
+}
+
+pub struct ShareDownload<'a> {
+    api: &'a PingvinApi,
+    share: RemoteShare,
+    token: Option<String>,
+    event_callback: Box<DownloadEventCallback>,
+
+    /// Skip files which already exist with the size of the file within the share
+    skip_existing: bool,
+}
+
+impl<'a> ShareDownload<'a> {
+    pub fn share(&self) -> &RemoteShare {
+        &self.share
+    }
+
+    pub fn with_callback(&mut self, callback: impl Fn(DownloadEvent) + 'static) -> &mut Self {
+        self.event_callback = Box::new(callback);
+        self
+    }
+
+    /// Skip files which already exist with the size of the file within the share instead of
+    /// downloading them again. The content is not compared, as the server provides no checksums.
+    pub fn set_skip_existing(&mut self, skip_existing: bool) -> &mut Self {
+        self.skip_existing = skip_existing;
+        self
+    }
+
+    /// Download the given files of the share into the directory.
+    /// Partially downloaded files will be resumed if the server supports range requests.
+    pub async fn download_files(
+        &self,
+        files: &[ShareFile],
+        directory: &Path,
+    ) -> anyhow::Result<()> {
+        let mut progress = DownloadProgress {
+            files_total: files.len() as u64,
+            bytes_total: files.iter().map(|file| file.size).sum(),
+            ..Default::default()
+        };
+
+        let mut files_failed = 0;
+        for file in files {
+            progress.file_name = file.name.clone();
+            let bytes_downloaded = progress.bytes_downloaded;
+
+            let result = async {
+                let path = target_path(directory, &file.name)?;
+                if self.skip_existing
+                    && fs::metadata(&path)
+                        .await
+                        .is_ok_and(|meta| meta.len() == file.size)
+                {
+                    (*self.event_callback)(DownloadEvent::FileSkipped { path });
+                    return Ok(());
+                }
+
+                let url = self.api.base_url.join(&format!(
+                    "shares/{}/files/{}?download=true",
+                    self.share.id, file.id
+                ))?;
+                self.download_to(url, &path, &mut progress).await?;
+                (*self.event_callback)(DownloadEvent::FileDownloaded { path });
+                anyhow::Ok(())
+            }
+            .await;
+
+            progress.files_downloaded += 1;
+            progress.bytes_downloaded = bytes_downloaded + file.size;
+            if let Err(error) = result {
+                files_failed += 1;
+                (*self.event_callback)(DownloadEvent::DownloadError {
+                    file: file.name.clone(),
+                    error: Arc::new(error),
+                });
+            }
+            (*self.event_callback)(DownloadEvent::DownloadProgress(progress.clone()));
+        }
+
+        if files_failed > 0 {
+            anyhow::bail!("{} file(s) failed to download", files_failed);
+        }
+
+        (*self.event_callback)(DownloadEvent::DownloadCompleted {
+            directory: directory.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    /// Download all files as a single zip archive created by the server.
+    /// Returns the path of the archive.
+    pub async fn download_zip(&self, directory: &Path) -> anyhow::Result<PathBuf> {
+        let name = self.share.name.as_deref().unwrap_or(&self.share.id);
+        let path = target_path(directory, &format!("{}.zip", name.replace('/', "_")))?;
+
+        let mut progress = DownloadProgress {
+            file_name: path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string(),
+            files_total: 1,
+            ..Default::default()
+        };
+
+        let url = self
+            .api
+            .base_url
+            .join(&format!("shares/{}/files/zip", self.share.id))?;
+        self.download_to(url, &path, &mut progress)
+            .await
+            .context("the zip archive of the share may not have been created yet")?;
+
+        progress.files_downloaded = 1;
+        (*self.event_callback)(DownloadEvent::DownloadProgress(progress));
+        (*self.event_callback)(DownloadEvent::FileDownloaded { path: path.clone() });
+        (*self.event_callback)(DownloadEvent::DownloadCompleted {
+            directory: directory.to_path_buf(),
+        });
+        Ok(path)
+    }
+
+    /// Stream the response into a partial file, which will be renamed once it is complete
+    async fn download_to(
+        &self,
+        url: Url,
+        path: &Path,
+        progress: &mut DownloadProgress,
+    ) -> anyhow::Result<()> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)
+                .await
+                .with_context(|| format!("create directory {}", directory.display()))?;
+        }
+
+        let part_path = partial_path(path);
+        let mut offset = fs::metadata(&part_path)
+            .await
+            .map(|meta| meta.len())
+            .unwrap_or(0);
+
+        let response = loop {
+            let mut request = self.api.http_client.get(url.clone()).headers(share_headers(
+                self.api,
+                &self.share.id,
+                self.token.as_deref(),
+            )?);
+            if offset > 0 {
+                request = request.header(RANGE, format!("bytes={}-", offset));
+            }
+
+            let response = request.send().await?;
+            if response.status() == StatusCode::RANGE_NOT_SATISFIABLE && offset > 0 {
+                log::debug!("Restarting the download of {}", path.display());
+                offset = 0;
+                continue;
+            }
+            break response.error_for_status()?;
+        };
+
+        let mut file = if response.status() == StatusCode::PARTIAL_CONTENT {
+            log::debug!(
+                "Resuming the download of {} at {} bytes",
+                path.display(),
+                offset
+            );
+            progress.bytes_downloaded += offset;
+            OpenOptions::new().append(true).open(&part_path).await?
+        } else {
+            File::create(&part_path)
+                .await
+                .with_context(|| format!("create {}", part_path.display()))?
+        };
+
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            file.write_all(&chunk).await?;
+
+            progress.bytes_downloaded += chunk.len() as u64;
+            (*self.event_callback)(DownloadEvent::DownloadProgress(progress.clone()));
+        }
+        file.flush().await?;
+        drop(file);
+
+        fs::rename(&part_path, path)
+            .await
+            .with_context(|| format!("write {}", path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, fs, path::Path, rc::Rc};
+
+    use super::{target_path, DownloadEvent, OpenShareResult};
+    use crate::api::{
+        mock::{MockFile, MockServer, MockShare},
+        PingvinApi,
+    };
+
+    fn mock_share(password: Option<&str>) -> MockShare {
+        MockShare {
+            name: Some("Photos".to_string()),
+            password: password.map(str::to_string),
+            zip: Some(b"zip archive".to_vec()),
+            files: vec![
+                MockFile {
+                    id: "file-0".to_string(),
+                    name: "a.txt".to_string(),
+                    data: (0..100u8).collect(),
+                    ..Default::default()
+                },
+                MockFile {
+                    id: "file-1".to_string(),
+                    name: "nested/b.txt".to_string(),
+                    data: b"second file".to_vec(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_target_path() {
+        let directory = Path::new("downloads");
+        assert_eq!(
+            target_path(directory, "a/b.txt").unwrap(),
+            directory.join("a").join("b.txt")
+        );
+        assert!(target_path(directory, "../b.txt").is_err());
+        assert!(target_path(directory, "/etc/passwd").is_err());
+        assert!(target_path(directory, "a\\..\\b.txt").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_download_share() {
+        let server = MockServer::start(16).await;
+        server
+            .state()
+            .shares
+            .insert("photos".to_string(), mock_share(None));
+        let directory = tempfile::tempdir().unwrap();
+
+        let api = PingvinApi::new(server.base_url()).unwrap();
+        let OpenShareResult::Opened(mut download) = api.open_share("photos", None).await.unwrap()
+        else {
+            panic!("expected the share to be opened");
+        };
+        assert_eq!(download.share().name.as_deref(), Some("Photos"));
+        assert_eq!(download.share().files.len(), 2);
+
+        /* a previous download has been interrupted after 40 bytes */
+        fs::write(
+            directory.path().join("a.txt.part"),
+            (0..40u8).collect::<Vec<_>>(),
+        )
+        .unwrap();
+
+        let events = Rc::new(RefCell::new(vec![]));
+        download.with_callback({
+            let events = events.clone();
+            move |event| events.borrow_mut().push(event)
+        });
+        let files = download.share().files.clone();
+        download
+            .download_files(&files, directory.path())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            fs::read(directory.path().join("a.txt")).unwrap(),
+            (0..100u8).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            fs::read(directory.path().join("nested/b.txt")).unwrap(),
+            b"second file"
+        );
+        assert!(!directory.path().join("a.txt.part").exists());
+        assert_eq!(server.state().range_requests, vec!["bytes=40-"]);
+
+        let progress = events
+            .borrow()
+            .iter()
+            .rev()
+            .find_map(|event| match event {
+                DownloadEvent::DownloadProgress(progress) => Some(progress.clone()),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(progress.files_downloaded, 2);
+        assert_eq!(progress.bytes_downloaded, 111);
+        assert_eq!(progress.bytes_total, 111);
+
+        /* existing files are downloaded again, even if only their content differs */
+        fs::write(directory.path().join("nested/b.txt"), b"other files").unwrap();
+        download
+            .download_files(&files, directory.path())
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read(directory.path().join("nested/b.txt")).unwrap(),
+            b"second file"
+        );
+
+        /* unless skipping existing files has been enabled */
+        events.borrow_mut().clear();
+        download
+            .set_skip_existing(true)
+            .download_files(&files, directory.path())
+            .await
+            .unwrap();
+        let skipped = events
+            .borrow()
+            .iter()
+            .filter(|event| matches!(event, DownloadEvent::FileSkipped { .. }))
+            .count();
+        assert_eq!(skipped, 2);
+
+        let path = download.download_zip(directory.path()).await.unwrap();
+        assert_eq!(path, directory.path().join("Photos.zip"));
+        assert_eq!(fs::read(path).unwrap(), b"zip archive");
+    }
+
+    #[tokio::test]
+    async fn test_download_password_protected_share() {
+        let server = MockServer::start(16).await;
+        server
+            .state()
+            .shares
+            .insert("secret".to_string(), mock_share(Some("hunter2")));
+        let directory = tempfile::tempdir().unwrap();
+
+        let api = PingvinApi::new(server.base_url()).unwrap();
+        assert!(matches!(
+            api.open_share("secret", None).await.unwrap(),
+            OpenShareResult::PasswordRequired
+        ));
+        assert!(api.open_share("secret", Some("wrong")).await.is_err());
+        assert!(api.open_share("missing", None).await.is_err());
+
+        let OpenShareResult::Opened(download) =
+            api.open_share("secret", Some("hunter2")).await.unwrap()
+        else {
+            panic!("expected the share to be opened");
+        };
+        let files = download.share().files[..1].to_vec();
+        download
+            .download_files(&files, directory.path())
+            .await
+            .unwrap();
+        assert_eq!(
+            fs::read(directory.path().join("a.txt")).unwrap(),
+            (0..100u8).collect::<Vec<_>>()
+        );
+        assert!(!directory.path().join("nested").exists());
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    fs::{self, File},
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use super::{write_json_atomic, UploadFile};
+
+/// Default directory where upload journals will be stored
+pub fn journal_directory() -> anyhow::Result<PathBuf> {
+    Ok(dirs::data_local_dir()
+        .context("missing local data directory")?
+        .join("pingvin")
+        .join("journal"))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalFile {
+    pub path: PathBuf,
+    pub length: u64,
+
+    /// The name the file is uploaded as.
+    /// Journals created by older versions do not contain a name.
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// The file id assigned by the server after the first chunk has been acknowledged
+    pub file_id: Option<String>,
+
+    /// Index of the last chunk which has been acknowledged by the server
+    pub chunk_index: Option<u64>,
+    pub completed: bool,
+}
+
+impl JournalFile {
+    pub fn upload_name(&self) -> String {
+        self.name.clone().unwrap_or_else(|| {
+            self.path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default()
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalState {
+    pub server_url: String,
+    pub share_id: String,
+    pub chunk_size: u64,
+    pub files: Vec<JournalFile>,
+
+    /// Whether the files are uploaded to a share which has been completed before.
+    /// Such shares are completed again if the upload fails and reopened to resume it.
+    #[serde(default)]
+    pub existing_share: bool,
+}
+
+/// Persistent record of a share upload, which allows to resume the upload
+/// after the process has been interrupted.
+#[derive(Debug)]
+pub struct UploadJournal {
+    path: PathBuf,
+    state: JournalState,
+}
+
+impl UploadJournal {
+    pub fn create(
+        directory: &Path,
+        server_url: &Url,
+        share_id: &str,
+        chunk_size: u64,
+        files: &[UploadFile],
+        existing_share: bool,
+    ) -> anyhow::Result<Self> {
+        fs::create_dir_all(directory)
+            .with_context(|| format!("create journal directory {}", directory.display()))?;
+
+        let files = files
+            .iter()
+            .map(|file| JournalFile {
+                path: file
+                    .path
+                    .canonicalize()
+                    .unwrap_or_else(|_| file.path.clone()),
+                length: file.path.metadata().map(|meta| meta.len()).unwrap_or(0),
+                name: Some(file.name.clone()),
+                file_id: None,
+                chunk_index: None,
+                completed: false,
+            })
+            .collect();
+
+        let journal = Self {
+            path: directory.join(format!("{}.json", share_id)),
+            state: JournalState {
+                server_url: server_url.to_string(),
+                share_id: share_id.to_string(),
+                chunk_size,
+                files,
+                existing_share,
+            },
+        };
+        journal.save()?;
+        Ok(journal)
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let reader = BufReader::new(
+            File::open(path).with_context(|| format!("open journal {}", path.display()))?,
+        );
+        let state = serde_json::from_reader(reader)
+            .with_context(|| format!("parse journal {}", path.display()))?;
+
+        Ok(Self {
+            path: path.to_owned(),
+            state,
+        })
+    }
+
+    /// Load the journal of a specific share from the journal directory
+    pub fn find(directory: &Path, share_id: &str) -> anyhow::Result<Self> {
+        let path = directory.join(format!("{}.json", share_id));
+        if !path.exists() {
+            anyhow::bail!("there is no pending upload for share {}", share_id);
+        }
+
+        Self::load(&path)
+    }
+
+    /// Load all journals stored within the journal directory
+    pub fn list(directory: &Path) -> anyhow::Result<Vec<Self>> {
+        if !directory.exists() {
+            return Ok(vec![]);
+        }
+
+        let mut journals = vec![];
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+
+            match Self::load(&path) {
+                Ok(journal) => journals.push(journal),
+                Err(err) => log::warn!("Skipping invalid journal: {:#}", err),
+            }
+        }
+
+        Ok(journals)
+    }
+
+    pub fn state(&self) -> &JournalState {
+        &self.state
+    }
+
+    pub fn server_url(&self) -> anyhow::Result<Url> {
+        Url::parse(&self.state.server_url).context("invalid journal server url")
+    }
+
+    /// Record that the server acknowledged a chunk of a file
+    pub fn record_chunk(
+        &mut self,
+        file_index: usize,
+        file_id: &str,
+        chunk_index: u64,
+    ) -> anyhow::Result<()> {
+        let file = self
+            .state
+            .files
+            .get_mut(file_index)
+            .context("invalid journal file index")?;
+
+        file.file_id = Some(file_id.to_string());
+        file.chunk_index = Some(chunk_index);
+        self.save()
+    }
+
+    pub fn record_completed(&mut self, file_index: usize) -> anyhow::Result<()> {
+        let file = self
+            .state
+            .files
+            .get_mut(file_index)
+            .context("invalid journal file index")?;
+
+        file.completed = true;
+        self.save()
+    }
+
+    /// Only keep the files which have not been uploaded completely.
+    /// Their upload will start over, as the chunks received so far are dropped once the share is completed.
+    pub fn retain_incomplete(&mut self) -> anyhow::Result<()> {
+        self.state.files.retain(|file| !file.completed);
+        for file in &mut self.state.files {
+            file.file_id = None;
+            file.chunk_index = None;
+        }
+        self.save()
+    }
+
+    /// Remove the journal once the share has been completed
+    pub fn remove(self) -> anyhow::Result<()> {
+        fs::remove_file(&self.path)
+            .with_context(|| format!("remove journal {}", self.path.display()))
+    }
+
+    fn save(&self) -> anyhow::Result<()> {
+        write_json_atomic(&self.path, &self.state).context("write journal")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use reqwest::Url;
+
+    use super::{UploadFile, UploadJournal};
+
+    #[test]
+    fn test_persist() {
+        let directory = tempfile::tempdir().unwrap();
+        let server_url = Url::parse("https://example.com/api/").unwrap();
+
+        let mut journal = UploadJournal::create(
+            directory.path(),
+            &server_url,
+            "share",
+            16,
+            &[
+                UploadFile {
+                    path: PathBuf::from("a.txt"),
+                    name: "a.txt".to_string(),
+                },
+                UploadFile {
+                    path: PathBuf::from("b.txt"),
+                    name: "docs/b.txt".to_string(),
+                },
+            ],
+            false,
+        )
+        .unwrap();
+        journal.record_chunk(1, "file-b", 3).unwrap();
+        journal.record_completed(0).unwrap();
+
+        let mut journal = UploadJournal::find(directory.path(), "share").unwrap();
+        assert_eq!(journal.state().share_id, "share");
+        assert_eq!(journal.server_url().unwrap(), server_url);
+        assert!(journal.state().files[0].completed);
+        assert_eq!(journal.state().files[1].file_id.as_deref(), Some("file-b"));
+        assert_eq!(journal.state().files[1].chunk_index, Some(3));
+        assert_eq!(journal.state().files[1].upload_name(), "docs/b.txt");
+
+        assert_eq!(UploadJournal::list(directory.path()).unwrap().len(), 1);
+
+        journal.retain_incomplete().unwrap();
+        let journal = UploadJournal::find(directory.path(), "share").unwrap();
+        assert_eq!(journal.state().files.len(), 1);
+        assert_eq!(journal.state().files[0].upload_name(), "docs/b.txt");
+        assert_eq!(journal.state().files[0].chunk_index, None);
+        journal.remove().unwrap();
+        assert!(UploadJournal::list(directory.path()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,825 @@
+//! Minimal in-process pingvin server used to test the API client.
+//! It only implements the parts of the HTTP protocol and the pingvin API the client uses.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use reqwest::Url;
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+#[derive(Debug, Default)]
+pub struct MockFile {
+    pub id: String,
+    pub name: String,
+    pub data: Vec<u8>,
+    pub chunks_received: u64,
+    pub completed: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct MockShare {
+    pub name: Option<String>,
+    pub expiration: String,
+    pub created_at: String,
+    pub views: u64,
+    pub password: Option<String>,
+    pub zip: Option<Vec<u8>>,
+    pub completed: bool,
+    pub recipients: Vec<String>,
+    pub files: Vec<MockFile>,
+
+    /// Files get numbered ids which are never reused, even if files have been removed
+    pub next_file_id: usize,
+
+    /// Token of the reverse share the share has been uploaded with
+    pub reverse_share_token: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct MockReverseShare {
+    pub id: String,
+    pub token: String,
+    pub max_share_size: u64,
+    pub remaining_uses: u32,
+    pub shares: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct MockState {
+    pub chunk_size: u64,
+    pub shares: HashMap<String, MockShare>,
+
+    /// Total number of chunk upload requests received
+    pub chunk_requests: usize,
+
+    /// Drop the connection while receiving the chunk upload requests with the given indices
+    pub drop_chunk_requests: Vec<usize>,
+
+    /// Reject the chunk upload requests with the given indices using the given status code
+    pub reject_chunk_requests: Vec<(usize, u16)>,
+
+    /// Delay before responding to a chunk upload request
+    pub chunk_delay: Option<Duration>,
+
+    /// Never respond to the chunk upload requests with the given indices
+    pub stall_chunk_requests: Vec<usize>,
+    pub active_chunk_requests: usize,
+    pub max_active_chunk_requests: usize,
+
+    /// Registered users and their passwords
+    pub users: HashMap<String, String>,
+
+    /// Users with two-factor authentication enabled and their currently valid TOTP code
+    pub totp_users: HashMap<String, String>,
+    pub login_tokens: HashMap<String, String>,
+    pub access_tokens: Vec<String>,
+    pub refresh_tokens: Vec<String>,
+    pub sign_in_requests: usize,
+    pub refresh_requests: usize,
+
+    /// Value of the `email.enableShareEmailRecipients` config entry
+    pub email_recipients: bool,
+
+    /// Fail completing shares with recipients as if the mail server is unreachable
+    pub mail_failure: bool,
+
+    /// Fail removing files from shares with an internal server error
+    pub delete_file_failure: bool,
+    pub complete_requests: usize,
+
+    /// Issued share tokens and the share they grant access to
+    pub share_tokens: HashMap<String, String>,
+
+    /// Range headers of all file download requests
+    pub range_requests: Vec<String>,
+
+    pub reverse_shares: Vec<MockReverseShare>,
+
+    /// OAuth providers and the user signing in with them
+    pub oauth_users: HashMap<String, String>,
+
+    /// Users the access and refresh tokens have been issued to
+    pub token_users: HashMap<String, String>,
+}
+
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    pub cookies: HashMap<String, String>,
+
+    /// Remaining headers with lowercase names
+    pub headers: HashMap<String, String>,
+    pub body: Vec<u8>,
+}
+
+pub struct Response {
+    pub status: u16,
+    pub payload: Value,
+
+    /// Raw response body which will be sent instead of the JSON payload
+    pub body: Option<Vec<u8>>,
+}
+
+impl Response {
+    pub fn json(status: u16, payload: Value) -> Self {
+        Self {
+            status,
+            payload,
+            body: None,
+        }
+    }
+
+    pub fn bytes(status: u16, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            payload: Value::Null,
+            body: Some(body),
+        }
+    }
+}
+
+pub struct MockServer {
+    base_url: Url,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockServer {
+    pub async fn start(chunk_size: u64) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url =
+            Url::parse(&format!("http://{}/api/", listener.local_addr().unwrap())).unwrap();
+
+        let state = Arc::new(Mutex::new(MockState {
+            chunk_size,
+            shares: Default::default(),
+            chunk_requests: 0,
+            drop_chunk_requests: vec![],
+            reject_chunk_requests: vec![],
+            chunk_delay: None,
+            stall_chunk_requests: vec![],
+            active_chunk_requests: 0,
+            max_active_chunk_requests: 0,
+            users: Default::default(),
+            totp_users: Default::default(),
+            login_tokens: Default::default(),
+            access_tokens: vec![],
+            refresh_tokens: vec![],
+            sign_in_requests: 0,
+            refresh_requests: 0,
+            email_recipients: false,
+            mail_failure: false,
+            delete_file_failure: false,
+            complete_requests: 0,
+            share_tokens: Default::default(),
+            range_requests: vec![],
+            reverse_shares: vec![],
+            oauth_users: Default::default(),
+            token_users: Default::default(),
+        }));
+
+        tokio::spawn({
+            let state = state.clone();
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(handle_connection(stream, state.clone()));
+                }
+            }
+        });
+
+        Self { base_url, state }
+    }
+
+    pub fn base_url(&self) -> Url {
+        self.base_url.clone()
+    }
+
+    pub fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+}
+
+async fn handle_connection(stream: TcpStream, state: Arc<Mutex<MockState>>) {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+        return;
+    }
+
+    let mut content_length = 0usize;
+    let mut cookies = HashMap::new();
+    let mut headers = HashMap::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+            return;
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().unwrap_or(0);
+            } else if name.eq_ignore_ascii_case("cookie") {
+                for cookie in value.split(';') {
+                    if let Some((name, value)) = cookie.trim().split_once('=') {
+                        cookies.insert(name.to_string(), value.to_string());
+                    }
+                }
+            } else {
+                headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+            }
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = Url::parse("http://localhost")
+        .unwrap()
+        .join(parts.next().unwrap_or("/"))
+        .unwrap();
+
+    let request = Request {
+        method,
+        path: target.path().to_string(),
+        query: target.query_pairs().into_owned().collect(),
+        cookies,
+        headers,
+        body: vec![],
+    };
+
+    let is_chunk_upload = request.method == "POST" && request.path.ends_with("/files");
+    let (drop_connection, reject_status, chunk_delay) = if is_chunk_upload {
+        let mut state = state.lock().unwrap();
+        let request_index = state.chunk_requests;
+        state.chunk_requests += 1;
+        state.active_chunk_requests += 1;
+        state.max_active_chunk_requests = state
+            .max_active_chunk_requests
+            .max(state.active_chunk_requests);
+
+        let reject_status = state
+            .reject_chunk_requests
+            .iter()
+            .find(|(index, _)| *index == request_index)
+            .map(|(_, status)| *status);
+        (
+            state.drop_chunk_requests.contains(&request_index),
+            reject_status,
+            match state.stall_chunk_requests.contains(&request_index) {
+                true => Some(Duration::from_secs(3600)),
+                false => state.chunk_delay,
+            },
+        )
+    } else {
+        (false, None, None)
+    };
+
+    if let Some(delay) = chunk_delay {
+        tokio::time::sleep(delay).await;
+    }
+    if is_chunk_upload {
+        state.lock().unwrap().active_chunk_requests -= 1;
+    }
+
+    if drop_connection {
+        /* receive a part of the body and then abort the connection */
+        let mut partial = vec![0u8; content_length / 2];
+        let _ = reader.read_exact(&mut partial).await;
+        return;
+    }
+
+    let mut body = vec![0u8; content_length];
+    if reader.read_exact(&mut body).await.is_err() {
+        return;
+    }
+
+    let request = Request { body, ..request };
+    let response = match reject_status {
+        Some(status) => Response::json(status, json!({ "message": "Rejected by mock" })),
+        None => handle_request(&mut state.lock().unwrap(), request),
+    };
+    let (content_type, payload) = match response.body {
+        Some(body) => ("application/octet-stream", body),
+        None => (
+            "application/json",
+            response.payload.to_string().into_bytes(),
+        ),
+    };
+
+    let mut stream = reader.into_inner();
+    let _ = stream
+        .write_all(
+            format!(
+                "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                response.status,
+                content_type,
+                payload.len(),
+            )
+            .as_bytes(),
+        )
+        .await;
+    let _ = stream.write_all(&payload).await;
+    let _ = stream.shutdown().await;
+}
+
+fn config_entry(key: &str, value_type: &str, value: &str) -> Value {
+    json!({ "key": key, "type": value_type, "value": value })
+}
+
+/// Check the share token cookie the same way the servers share security guard does
+fn check_share_token(state: &MockState, request: &Request, share_id: &str) -> Option<Response> {
+    let share = state.shares.get(share_id)?;
+    let token = request.cookies.get(&format!("share_{}_token", share_id));
+    if share.password.is_some() && token.is_none() {
+        return Some(Response::json(
+            403,
+            json!({ "message": "This share is password protected", "error": "share_password_required" }),
+        ));
+    }
+
+    if !token.is_some_and(|token| {
+        state
+            .share_tokens
+            .get(token)
+            .is_some_and(|id| id == share_id)
+    }) {
+        return Some(Response::json(
+            403,
+            json!({ "message": "Share token required", "error": "share_token_required" }),
+        ));
+    }
+
+    None
+}
+
+/// Access token sent as cookie, the server ignores bearer tokens
+fn access_token(request: &Request) -> Option<&str> {
+    request.cookies.get("access_token").map(String::as_str)
+}
+
+/// Whether the request contains a valid access token
+fn is_authorized(state: &MockState, request: &Request) -> bool {
+    access_token(request)
+        .is_some_and(|token| state.access_tokens.iter().any(|valid| valid == token))
+}
+
+fn handle_request(state: &mut MockState, request: Request) -> Response {
+    let segments = request
+        .path
+        .trim_start_matches("/api/")
+        .split('/')
+        .collect::<Vec<_>>();
+
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["configs"]) => Response::json(
+            200,
+            json!([
+                config_entry("general.appUrl", "string", "http://pingvin.local"),
+                config_entry("share.allowUnauthenticatedShares", "boolean", "true"),
+                config_entry("share.chunkSize", "number", &state.chunk_size.to_string()),
+                config_entry(
+                    "email.enableShareEmailRecipients",
+                    "boolean",
+                    &state.email_recipients.to_string()
+                ),
+            ]),
+        ),
+        ("POST", ["auth", "signIn"]) => {
+            state.sign_in_requests += 1;
+
+            let payload: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let username = payload["username"].as_str().unwrap_or_default();
+            let password = payload["password"].as_str().unwrap_or_default();
+            if state
+                .users
+                .get(username)
+                .is_none_or(|value| value != password)
+            {
+                return Response::json(401, json!({ "message": "Wrong email or password" }));
+            }
+
+            if state.totp_users.contains_key(username) {
+                let login_token = format!("login-{}", state.login_tokens.len());
+                state
+                    .login_tokens
+                    .insert(login_token.clone(), username.to_string());
+                return Response::json(200, json!({ "loginToken": login_token }));
+            }
+
+            let access_token = format!("access-{}", state.access_tokens.len());
+            let refresh_token = format!("refresh-{}", state.refresh_tokens.len());
+            state.access_tokens.push(access_token.clone());
+            state.refresh_tokens.push(refresh_token.clone());
+            state
+                .token_users
+                .insert(access_token.clone(), username.to_string());
+            Response::json(
+                200,
+                json!({ "accessToken": access_token, "refreshToken": refresh_token }),
+            )
+        }
+        ("POST", ["auth", "signIn", "totp"]) => {
+            let payload: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let username = payload["loginToken"]
+                .as_str()
+                .and_then(|token| state.login_tokens.get(token));
+            let expected_code = username.and_then(|username| state.totp_users.get(username));
+            if expected_code.is_none_or(|code| Some(code.as_str()) != payload["totp"].as_str()) {
+                return Response::json(401, json!({ "message": "Invalid totp code" }));
+            }
+
+            let access_token = format!("access-{}", state.access_tokens.len());
+            state.access_tokens.push(access_token.clone());
+            Response::json(200, json!({ "accessToken": access_token }))
+        }
+        ("POST", ["auth", "token"]) => {
+            state.refresh_requests += 1;
+
+            let refresh_token = request.cookies.get("refresh_token");
+            if !refresh_token.is_some_and(|token| state.refresh_tokens.contains(token)) {
+                return Response::json(401, json!({ "message": "Unauthorized" }));
+            }
+
+            let access_token = format!("access-{}", state.access_tokens.len());
+            state.access_tokens.push(access_token.clone());
+            if let Some(username) = refresh_token.and_then(|token| state.token_users.get(token)) {
+                let username = username.clone();
+                state.token_users.insert(access_token.clone(), username);
+            }
+            Response::json(200, json!({ "accessToken": access_token }))
+        }
+        ("GET", ["users", "me"]) => {
+            if !is_authorized(state, &request) {
+                return Response::json(401, json!({ "message": "Unauthorized" }));
+            }
+
+            let username = access_token(&request)
+                .and_then(|token| state.token_users.get(token))
+                .map_or("user", String::as_str);
+            Response::json(200, json!({ "id": "user", "username": username }))
+        }
+        ("GET", ["oauth", "available"]) => {
+            Response::json(200, json!(state.oauth_users.keys().collect::<Vec<_>>()))
+        }
+        ("GET", ["shares"]) => {
+            if !is_authorized(state, &request) {
+                return Response::json(401, json!({ "message": "Unauthorized" }));
+            }
+
+            /* like the server, skip shares which already expired */
+            let now = chrono::Utc::now();
+            let mut shares = state
+                .shares
+                .iter()
+                .filter(|(_, share)| {
+                    chrono::DateTime::parse_from_rfc3339(&share.expiration)
+                        .map_or(true, |expiration| {
+                            expiration.timestamp() == 0 || expiration > now
+                        })
+                })
+                .collect::<Vec<_>>();
+            shares.sort_by_key(|(id, _)| *id);
+            let shares = shares
+                .into_iter()
+                .map(|(id, share)| {
+                    json!({
+                        "id": id,
+                        "name": share.name,
+                        "expiration": share.expiration,
+                        "createdAt": share.created_at,
+                        "views": share.views,
+                        "files": share.files.iter().map(|file| json!({
+                            "id": file.id,
+                            "name": file.name,
+                            "size": file.data.len().to_string(),
+                        })).collect::<Vec<_>>(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            Response::json(200, json!(shares))
+        }
+        ("GET", ["shares", share_id]) => {
+            if let Some(response) = check_share_token(state, &request, share_id) {
+                return response;
+            }
+            let Some(share) = state.shares.get(*share_id) else {
+                return Response::json(404, json!({ "message": "Share not found" }));
+            };
+
+            Response::json(
+                200,
+                json!({
+                    "id": share_id,
+                    "name": share.name,
+                    "files": share.files.iter().map(|file| json!({
+                        "id": file.id,
+                        "name": file.name,
+                        "size": file.data.len().to_string(),
+                    })).collect::<Vec<_>>(),
+                }),
+            )
+        }
+        ("POST", ["shares", share_id, "token"]) => {
+            let Some(share) = state.shares.get(*share_id) else {
+                return Response::json(404, json!({ "message": "Share not found" }));
+            };
+
+            let payload: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            if share
+                .password
+                .as_deref()
+                .is_some_and(|password| payload["password"].as_str() != Some(password))
+            {
+                return Response::json(
+                    403,
+                    json!({ "message": "Wrong password", "error": "share_password_wrong" }),
+                );
+            }
+
+            let token = format!("share-token-{}", state.share_tokens.len());
+            state
+                .share_tokens
+                .insert(token.clone(), share_id.to_string());
+            Response::json(200, json!({ "token": token }))
+        }
+        ("GET", ["shares", share_id, "files", "zip"]) => {
+            if let Some(response) = check_share_token(state, &request, share_id) {
+                return response;
+            }
+
+            match state
+                .shares
+                .get(*share_id)
+                .and_then(|share| share.zip.clone())
+            {
+                Some(zip) => Response::bytes(200, zip),
+                None => Response::json(404, json!({ "message": "Zip not found" })),
+            }
+        }
+        ("GET", ["shares", share_id, "files", file_id]) => {
+            if let Some(response) = check_share_token(state, &request, share_id) {
+                return response;
+            }
+            let Some(data) = state.shares.get(*share_id).and_then(|share| {
+                share
+                    .files
+                    .iter()
+                    .find(|file| file.id == *file_id)
+                    .map(|file| file.data.clone())
+            }) else {
+                return Response::json(404, json!({ "message": "File not found" }));
+            };
+
+            let Some(range) = request.headers.get("range") else {
+                return Response::bytes(200, data);
+            };
+            state.range_requests.push(range.clone());
+
+            let start = range
+                .strip_prefix("bytes=")
+                .and_then(|range| range.strip_suffix('-'))
+                .and_then(|start| start.parse::<usize>().ok())
+                .unwrap_or_default();
+            if start >= data.len() {
+                return Response::json(416, json!({ "message": "Range not satisfiable" }));
+            }
+            Response::bytes(206, data[start..].to_vec())
+        }
+        ("DELETE", ["shares", share_id]) => {
+            if !is_authorized(state, &request) {
+                return Response::json(401, json!({ "message": "Unauthorized" }));
+            }
+
+            match state.shares.remove(*share_id) {
+                Some(_) => Response::json(200, json!({})),
+                None => Response::json(404, json!({ "message": "Share not found" })),
+            }
+        }
+        ("POST", ["reverseShares"]) => {
+            if !is_authorized(state, &request) {
+                return Response::json(401, json!({ "message": "Unauthorized" }));
+            }
+
+            let payload: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let token = format!("reverse-token-{}", state.reverse_shares.len());
+            state.reverse_shares.push(MockReverseShare {
+                id: format!("reverse-{}", state.reverse_shares.len()),
+                token: token.clone(),
+                max_share_size: payload["maxShareSize"]
+                    .as_str()
+                    .and_then(|size| size.parse().ok())
+                    .unwrap_or_default(),
+                remaining_uses: payload["maxUseCount"].as_u64().unwrap_or_default() as u32,
+                shares: vec![],
+            });
+            Response::json(
+                201,
+                json!({ "token": token, "link": format!("http://pingvin.local/upload/{}", token) }),
+            )
+        }
+        ("GET", ["reverseShares"]) => {
+            if !is_authorized(state, &request) {
+                return Response::json(401, json!({ "message": "Unauthorized" }));
+            }
+
+            let reverse_shares = state
+                .reverse_shares
+                .iter()
+                .map(|reverse_share| {
+                    json!({
+                        "id": reverse_share.id,
+                        "token": reverse_share.token,
+                        "maxShareSize": reverse_share.max_share_size.to_string(),
+                        "shareExpiration": "2030-01-01T00:00:00.000Z",
+                        "remainingUses": reverse_share.remaining_uses,
+                        "shares": reverse_share.shares.iter().map(|id| json!({ "id": id })).collect::<Vec<_>>(),
+                    })
+                })
+                .collect::<Vec<_>>();
+            Response::json(200, json!(reverse_shares))
+        }
+        ("GET", ["reverseShares", token]) => {
+            match state.reverse_shares.iter().find(|reverse_share| {
+                reverse_share.token == *token && reverse_share.remaining_uses > 0
+            }) {
+                Some(reverse_share) => Response::json(
+                    200,
+                    json!({
+                        "id": reverse_share.id,
+                        "token": reverse_share.token,
+                        "maxShareSize": reverse_share.max_share_size.to_string(),
+                    }),
+                ),
+                None => Response::json(404, json!({ "message": "Reverse share not found" })),
+            }
+        }
+        ("DELETE", ["reverseShares", id]) => {
+            if !is_authorized(state, &request) {
+                return Response::json(401, json!({ "message": "Unauthorized" }));
+            }
+
+            let count = state.reverse_shares.len();
+            state
+                .reverse_shares
+                .retain(|reverse_share| reverse_share.id != *id);
+            if state.reverse_shares.len() == count {
+                return Response::json(404, json!({ "message": "Reverse share not found" }));
+            }
+            Response::json(200, json!({}))
+        }
+        ("POST", ["shares"]) => {
+            let payload: Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let id = payload["id"].as_str().unwrap_or("share").to_string();
+            if state.shares.contains_key(&id) {
+                return Response::json(400, json!({ "message": "Share id already in use" }));
+            }
+            if payload["security"]["password"]
+                .as_str()
+                .is_some_and(|password| !(3..=30).contains(&password.chars().count()))
+            {
+                return Response::json(
+                    400,
+                    json!({
+                        "message": ["security.password must be longer than or equal to 3 characters"],
+                        "error": "Bad Request"
+                    }),
+                );
+            }
+
+            let reverse_share_token = request.cookies.get("reverse_share_token").cloned();
+            if let Some(token) = &reverse_share_token {
+                let Some(reverse_share) = state.reverse_shares.iter_mut().find(|reverse_share| {
+                    reverse_share.token == *token && reverse_share.remaining_uses > 0
+                }) else {
+                    return Response::json(
+                        403,
+                        json!({ "message": "Reverse share token invalid" }),
+                    );
+                };
+                reverse_share.remaining_uses -= 1;
+                reverse_share.shares.push(id.clone());
+            }
+
+            let recipients = payload["recipients"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|value| value.as_str().map(str::to_string))
+                .collect();
+            state.shares.insert(
+                id.clone(),
+                MockShare {
+                    name: payload["name"].as_str().map(str::to_string),
+                    expiration: "1970-01-01T00:00:00.000Z".to_string(),
+                    created_at: "2024-01-01T00:00:00.000Z".to_string(),
+                    recipients,
+                    reverse_share_token,
+                    ..Default::default()
+                },
+            );
+            Response::json(201, json!({ "id": id }))
+        }
+        ("POST", ["shares", share_id, "files"]) => {
+            let chunk_size = state.chunk_size;
+            let Some(share) = state.shares.get_mut(*share_id) else {
+                return Response::json(404, json!({ "message": "Share not found" }));
+            };
+            if share.completed {
+                return Response::json(400, json!({ "message": "Share is already completed" }));
+            }
+
+            let chunk_index: u64 = request.query["chunkIndex"].parse().unwrap();
+            let total_chunks: u64 = request.query["totalChunks"].parse().unwrap();
+
+            let file_index = match request.query.get("id") {
+                Some(id) => share.files.iter().position(|file| &file.id == id),
+                None => None,
+            };
+            let file_index = match file_index {
+                Some(index) => index,
+                None => {
+                    share.next_file_id += 1;
+                    share.files.push(MockFile {
+                        id: format!("file-{}", share.next_file_id - 1),
+                        name: request.query["name"].clone(),
+                        ..Default::default()
+                    });
+                    share.files.len() - 1
+                }
+            };
+
+            let file = &mut share.files[file_index];
+            let expected_chunk_index = (file.data.len() as u64).div_ceil(chunk_size);
+            if chunk_index != expected_chunk_index {
+                return Response::json(
+                    400,
+                    json!({
+                        "statusCode": 400,
+                        "message": "Unexpected chunk received",
+                        "error": "unexpected_chunk_index",
+                        "expectedChunkIndex": expected_chunk_index,
+                    }),
+                );
+            }
+
+            file.data.extend_from_slice(&request.body);
+            file.chunks_received += 1;
+            file.completed = chunk_index + 1 == total_chunks;
+            Response::json(201, json!({ "id": file.id, "name": file.name }))
+        }
+        ("DELETE", ["shares", share_id, "files", file_id]) => {
+            if !is_authorized(state, &request) {
+                return Response::json(401, json!({ "message": "Unauthorized" }));
+            }
+            let Some(share) = state.shares.get_mut(*share_id) else {
+                return Response::json(404, json!({ "message": "Share not found" }));
+            };
+            let Some(index) = share.files.iter().position(|file| file.id == *file_id) else {
+                return Response::json(404, json!({ "message": "File not found" }));
+            };
+            if state.delete_file_failure {
+                return Response::json(500, json!({ "message": "Internal server error" }));
+            }
+
+            share.files.remove(index);
+            Response::json(200, json!({}))
+        }
+        ("DELETE", ["shares", share_id, "complete"]) => {
+            if !is_authorized(state, &request) {
+                return Response::json(401, json!({ "message": "Unauthorized" }));
+            }
+            let Some(share) = state.shares.get_mut(*share_id) else {
+                return Response::json(404, json!({ "message": "Share not found" }));
+            };
+
+            share.completed = false;
+            Response::json(200, json!({ "id": share_id }))
+        }
+        ("POST", ["shares", share_id, "complete"]) => {
+            state.complete_requests += 1;
+            let mail_failure = state.mail_failure;
+            let Some(share) = state.shares.get_mut(*share_id) else {
+                return Response::json(404, json!({ "message": "Share not found" }));
+            };
+            if mail_failure && !share.recipients.is_empty() {
+                return Response::json(500, json!({ "message": "Failed to send email" }));
+            }
+
+            share.completed = true;
+            Response::json(202, json!({ "id": share_id }))
+        }
+        _ => Response::json(404, json!({ "message": "Not found" })),
+    }
+}
@@ -1,36 +1,126 @@
 mod config;
 use std::{
     borrow::Cow,
-    io::SeekFrom,
+    cell::RefCell,
     path::{Path, PathBuf},
     sync::{
         atomic::{AtomicU64, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::Context;
 pub use config::*;
 
+mod auth;
+pub use auth::*;
+
+mod chunks;
+use chunks::ChunkSource;
+
+mod download;
+pub use download::*;
+
+mod reverse_share;
+pub use reverse_share::*;
+
+mod journal;
+pub use journal::*;
+
+mod oauth;
+pub use oauth::*;
+
+#[cfg(test)]
+mod mock;
+
+mod retry;
+pub use retry::*;
+
+mod session;
+pub use session::*;
+
 mod share;
 use futures::StreamExt;
 use rand::{distributions::Alphanumeric, Rng};
-use reqwest::{header::HeaderMap, Body, Client, StatusCode, Url};
+use reqwest::{
+    header::{HeaderMap, COOKIE},
+    Body, Client, StatusCode, Url,
+};
 use serde::{Deserialize, Serialize};
 pub use share::*;
-use tokio::{
-    fs::File,
-    io::{AsyncReadExt, AsyncSeekExt},
-    time,
-};
-use tokio_util::codec::{BytesCodec, FramedRead};
+use tokio::{fs::File, io::AsyncRead, time};
+
+/// Time to establish a connection to the server
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Like `error_for_status`, but fails with the reason the server gave for rejecting the request.
+/// Validation failures contain one message per invalid field.
+async fn error_for_status_message(
+    response: reqwest::Response,
+) -> anyhow::Result<reqwest::Response> {
+    let status = response.status();
+    if !status.is_client_error() {
+        return Ok(response.error_for_status()?);
+    }
+
+    let payload = response
+        .json::<serde_json::Value>()
+        .await
+        .unwrap_or_default();
+    let message = match &payload["message"] {
+        serde_json::Value::String(message) => message.clone(),
+        serde_json::Value::Array(messages) => messages
+            .iter()
+            .filter_map(serde_json::Value::as_str)
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => anyhow::bail!("the server rejected the request ({})", status),
+    };
+    anyhow::bail!("the server rejected the request ({}): {}", status, message)
+}
+
+/// Add a cookie to the cookies already contained within the headers
+fn append_cookie(headers: &mut HeaderMap, name: &str, value: &str) -> anyhow::Result<()> {
+    let cookie = format!("{}={}", name, value);
+    let cookie = match headers.get(COOKIE) {
+        Some(existing) => format!("{}; {}", existing.to_str()?, cookie),
+        None => cookie,
+    };
+    headers.insert(COOKIE, cookie.parse()?);
+    Ok(())
+}
+
+/// Write the value as JSON, readable by the current user only.
+/// The value is written to a temporary file first, so a crash never leaves a truncated file behind.
+fn write_json_atomic(path: &Path, value: &impl Serialize) -> anyhow::Result<()> {
+    let temp_path = path.with_extension("json.tmp");
+    let _ = std::fs::remove_file(&temp_path);
+    {
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut writer = std::io::BufWriter::new(
+            options
+                .open(&temp_path)
+                .with_context(|| format!("create {}", temp_path.display()))?,
+        );
+        serde_json::to_writer_pretty(&mut writer, value)?;
+        std::io::Write::flush(&mut writer)?;
+    }
+
+    std::fs::rename(&temp_path, path).with_context(|| format!("write {}", path.display()))
+}
 
 pub struct PingvinApi {
     base_url: Url,
     http_client: Client,
 
     authentication_headers: HeaderMap,
+    session: Option<Session>,
 }
 
 impl PingvinApi {
@@ -39,29 +129,77 @@ impl PingvinApi {
         let _ = base_url.set_username("");
         Ok(Self {
             base_url,
-            http_client: Client::builder().build()?,
+            http_client: Client::builder().connect_timeout(CONNECT_TIMEOUT).build()?,
 
             authentication_headers: HeaderMap::new(),
+            session: None,
         })
     }
 
-    pub async fn login(&mut self, username: &str, password: &str) -> anyhow::Result<bool> {
-        #[derive(Serialize)]
-        struct Request<'a> {
-            username: &'a str,
-            password: &'a str,
+    /// The session of the signed in user
+    pub fn session(&self) -> Option<&Session> {
+        self.session.as_ref()
+    }
+
+    /// Authenticate all following requests using the given session
+    pub fn set_session(&mut self, session: Session) -> anyhow::Result<()> {
+        self.authentication_headers.insert(
+            "Cookie",
+            format!("access_token={}", session.access_token).parse()?,
+        );
+        self.session = Some(session);
+        Ok(())
+    }
+
+    /// Check if the current session is still accepted by the server
+    pub async fn validate_session(&self) -> anyhow::Result<bool> {
+        let response = self
+            .http_client
+            .get(self.base_url.join("users/me")?)
+            .headers(self.authentication_headers.clone())
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::UNAUTHORIZED {
+            return Ok(false);
         }
 
+        response.error_for_status()?;
+        Ok(true)
+    }
+
+    /// The signed in user
+    pub async fn current_user(&self) -> anyhow::Result<CurrentUser> {
+        let response = self
+            .http_client
+            .get(self.base_url.join("users/me")?)
+            .headers(self.authentication_headers.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+
+    /// Obtain a new access token using the refresh token of the current session
+    pub async fn refresh_session(&mut self) -> anyhow::Result<bool> {
         #[derive(Deserialize)]
         #[serde(rename_all = "camelCase")]
         struct Response {
             access_token: String,
         }
 
+        let Some(refresh_token) = self
+            .session
+            .as_ref()
+            .and_then(|session| session.refresh_token.clone())
+        else {
+            return Ok(false);
+        };
+
         let response = self
             .http_client
-            .post(self.base_url.join("auth/signIn")?)
-            .json(&Request { password, username })
+            .post(self.base_url.join("auth/token")?)
+            .header("Cookie", format!("refresh_token={}", refresh_token))
             .send()
             .await?;
 
@@ -72,13 +210,106 @@ impl PingvinApi {
         response.error_for_status_ref()?;
         let response = response.json::<Response>().await?;
 
-        self.authentication_headers.insert(
-            "Cookie",
-            format!("access_token={}", response.access_token).parse()?,
-        );
+        self.set_session(Session {
+            access_token: response.access_token,
+            refresh_token: Some(refresh_token),
+        })?;
         Ok(true)
     }
 
+    /// Sign in using the refresh token of a session established elsewhere, e.g. within the browser
+    pub async fn login_refresh_token(
+        &mut self,
+        refresh_token: &str,
+    ) -> anyhow::Result<LoginResult> {
+        self.session = Some(Session {
+            access_token: String::new(),
+            refresh_token: Some(refresh_token.to_string()),
+        });
+        if !self.refresh_session().await? {
+            self.session = None;
+            return Ok(LoginResult::Rejected);
+        }
+        Ok(LoginResult::LoggedIn)
+    }
+
+    pub async fn login(&mut self, username: &str, password: &str) -> anyhow::Result<LoginResult> {
+        #[derive(Serialize)]
+        struct Request<'a> {
+            username: &'a str,
+            password: &'a str,
+        }
+
+        let response = self
+            .http_client
+            .post(self.base_url.join("auth/signIn")?)
+            .json(&Request { password, username })
+            .send()
+            .await?;
+
+        self.handle_login_response(response).await
+    }
+
+    /// Complete a login which requires a second factor using the current TOTP code
+    pub async fn login_totp(
+        &mut self,
+        login_token: &str,
+        totp: &str,
+    ) -> anyhow::Result<LoginResult> {
+        #[derive(Serialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Request<'a> {
+            totp: &'a str,
+            login_token: &'a str,
+        }
+
+        let response = self
+            .http_client
+            .post(self.base_url.join("auth/signIn/totp")?)
+            .json(&Request { totp, login_token })
+            .send()
+            .await?;
+
+        self.handle_login_response(response).await
+    }
+
+    async fn handle_login_response(
+        &mut self,
+        response: reqwest::Response,
+    ) -> anyhow::Result<LoginResult> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Response {
+            access_token: Option<String>,
+
+            #[serde(default)]
+            refresh_token: Option<String>,
+
+            /// Returned instead of the tokens if the user has to provide a TOTP code
+            #[serde(default)]
+            login_token: Option<String>,
+        }
+
+        if response.status() == StatusCode::UNAUTHORIZED {
+            return Ok(LoginResult::Rejected);
+        }
+
+        response.error_for_status_ref()?;
+        let response = response.json::<Response>().await?;
+
+        match (response.access_token, response.login_token) {
+            (Some(access_token), _) => {
+                self.set_session(Session {
+                    access_token,
+                    refresh_token: response.refresh_token,
+                })?;
+                Ok(LoginResult::LoggedIn)
+            }
+            (None, Some(login_token)) => Ok(LoginResult::TotpRequired { login_token }),
+            (None, None) => anyhow::bail!("login response contains no access token"),
+        }
+    }
+
     pub async fn public_config(&self) -> anyhow::Result<PublicConfiguration> {
         let response = reqwest::get(self.base_url.join("configs")?)
             .await?
@@ -86,7 +317,43 @@ impl PingvinApi {
         Ok(PublicConfiguration::new(response.json().await?))
     }
 
-    pub fn create_share(&self) -> ShareBuilder {
+    /// Shares created by the signed in user.
+    /// The server omits shares which already expired.
+    pub async fn list_shares(&self) -> anyhow::Result<Vec<MyShare>> {
+        let response = self
+            .http_client
+            .get(self.base_url.join("shares")?)
+            .headers(self.authentication_headers.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+
+    pub async fn delete_share(&self, share_id: &str) -> anyhow::Result<()> {
+        self.http_client
+            .delete(self.base_url.join(&format!("shares/{}", share_id))?)
+            .headers(self.authentication_headers.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    pub async fn delete_share_file(&self, share_id: &str, file_id: &str) -> anyhow::Result<()> {
+        self.http_client
+            .delete(
+                self.base_url
+                    .join(&format!("shares/{}/files/{}", share_id, file_id))?,
+            )
+            .headers(self.authentication_headers.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    pub fn create_share(&self) -> ShareBuilder<'_> {
         ShareBuilder {
             api: self,
 
@@ -96,12 +363,102 @@ impl PingvinApi {
 
             expiration: ExpireDuration::Never,
             files: vec![],
+            streams: vec![],
             recipients: vec![],
 
             security: ShareSecurityOptions::default(),
             event_callback: Box::new(|_| {}),
+            retry_policy: RetryPolicy::default(),
+            parallel_uploads: 1,
+
+            journal_directory: None,
+            journal: None,
+            existing_share: false,
+            removed_files: vec![],
         }
     }
+
+    /// Upload additional files to an existing share of the signed in user.
+    /// The share will be reopened for the upload and completed again afterwards.
+    pub fn append_to_share(&self, share_id: String) -> ShareBuilder<'_> {
+        let mut builder = self.create_share();
+        builder.id = Some(share_id);
+        builder.existing_share = true;
+        builder
+    }
+
+    /// Continue an interrupted upload recorded within the given journal.
+    /// Files which already have been uploaded completely will be skipped.
+    pub fn resume_share(&self, journal: UploadJournal) -> ShareBuilder<'_> {
+        let mut builder = self.create_share();
+        builder.id = Some(journal.state().share_id.clone());
+        builder.files = journal
+            .state()
+            .files
+            .iter()
+            .map(|file| UploadFile {
+                path: file.path.clone(),
+                name: file.upload_name(),
+            })
+            .collect();
+        builder.existing_share = journal.state().existing_share;
+        builder.journal = Some(journal);
+        builder
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CurrentUser {
+    pub username: String,
+    /* other members have been omitted */
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoginResult {
+    LoggedIn,
+
+    /// The server rejected the credentials
+    Rejected,
+
+    /// The user has two-factor authentication enabled.
+    /// The login has to be completed with a TOTP code using the login token.
+    TotpRequired {
+        login_token: String,
+    },
+}
+
+/// A local file and the name it will be uploaded as
+#[derive(Debug, Clone)]
+pub struct UploadFile {
+    pub path: PathBuf,
+    pub name: String,
+}
+
+/// Data of unknown length read from a stream, like an archive created on the fly.
+/// The total chunk count is only known to the server once the stream has ended.
+pub struct UploadStream {
+    pub name: String,
+    pub reader: Box<dyn AsyncRead + Unpin>,
+}
+
+/// A share after the upload, containing the files which have been uploaded successfully
+#[derive(Debug, Clone)]
+pub struct UploadedShare {
+    pub id: String,
+    pub files: Vec<UploadedFile>,
+}
+
+#[derive(Debug, Clone)]
+pub struct UploadedFile {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FileProgress {
+    pub file_index: usize,
+    pub file: PathBuf,
+    pub length: u64,
+    pub bytes_uploaded: u64,
 }
 
 #[derive(Debug, Clone, Default)]
@@ -110,9 +467,67 @@ pub struct UploadProgress {
     pub files_uploaded: u64,
     pub files_failed: u64,
 
-    pub file_current: PathBuf,
-    pub file_length: u64,
-    pub file_bytes_uploaded: u64,
+    /// Accumulated size of all files
+    pub bytes_total: u64,
+
+    /// Bytes uploaded across all files including the files currently being uploaded
+    pub bytes_uploaded: u64,
+
+    /// Files which are currently being uploaded
+    pub files_active: Vec<FileProgress>,
+}
+
+impl UploadProgress {
+    fn file_started(&mut self, file_index: usize, file: &Path, length: u64) {
+        self.files_active.push(FileProgress {
+            file_index,
+            file: file.to_owned(),
+            length,
+            bytes_uploaded: 0,
+        });
+    }
+
+    fn file_progress(&mut self, file_index: usize, bytes_uploaded: u64) {
+        let Some(entry) = self
+            .files_active
+            .iter_mut()
+            .find(|entry| entry.file_index == file_index)
+        else {
+            return;
+        };
+
+        if bytes_uploaded > entry.length {
+            /* the length of streams grows while they are being read */
+            self.bytes_total += bytes_uploaded - entry.length;
+            entry.length = bytes_uploaded;
+        }
+
+        self.bytes_uploaded =
+            (self.bytes_uploaded + bytes_uploaded).saturating_sub(entry.bytes_uploaded);
+        entry.bytes_uploaded = bytes_uploaded;
+    }
+
+    fn file_finished(&mut self, file_index: usize, success: bool) {
+        if let Some(position) = self
+            .files_active
+            .iter()
+            .position(|entry| entry.file_index == file_index)
+        {
+            let entry = self.files_active.remove(position);
+            if success {
+                self.bytes_uploaded =
+                    (self.bytes_uploaded + entry.length).saturating_sub(entry.bytes_uploaded);
+            } else {
+                self.bytes_uploaded = self.bytes_uploaded.saturating_sub(entry.bytes_uploaded);
+            }
+        }
+
+        if success {
+            self.files_uploaded += 1;
+        } else {
+            self.files_failed += 1;
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -120,6 +535,11 @@ pub enum UploadEvent {
     ShareCreated {
         share_id: String,
     },
+
+    /// An existing share has been reopened to upload additional files
+    ShareReopened {
+        share_id: String,
+    },
     ShareCompleted,
 
     UploadProgress(UploadProgress),
@@ -127,6 +547,61 @@ pub enum UploadEvent {
         file: PathBuf,
         error: Arc<anyhow::Error>,
     },
+
+    /// Uploading a chunk failed with a transient error and will be attempted again
+    UploadRetry {
+        file: PathBuf,
+        chunk_index: usize,
+        chunk_count: usize,
+        attempt: u32,
+        error: Arc<anyhow::Error>,
+    },
+
+    /// Result of notifying a share recipient by email
+    RecipientNotified {
+        recipient: String,
+        delivery: RecipientDelivery,
+    },
+}
+
+/// Delivery result of the email notification sent to a single share recipient
+#[derive(Debug, Clone)]
+pub enum RecipientDelivery {
+    /// The server sent the notification
+    Sent,
+
+    /// Sending the notification to the recipient failed
+    Failed(Arc<anyhow::Error>),
+
+    /// Sending the notification to at least one recipient failed, but the server does not
+    /// report which ones. The notification to this recipient may have been sent nevertheless.
+    Unconfirmed(Arc<anyhow::Error>),
+}
+
+impl RecipientDelivery {
+    /// Derive the delivery result of every recipient from the result of completing the share.
+    /// The server sends one mail per recipient while completing the share and fails the
+    /// request with a generic error if any of them could not be sent.
+    fn from_completion(
+        recipients: &[String],
+        completed: &anyhow::Result<()>,
+    ) -> Vec<(String, RecipientDelivery)> {
+        let delivery = match completed {
+            Ok(()) => RecipientDelivery::Sent,
+            Err(err) => {
+                let error = Arc::new(anyhow::anyhow!("{:#}", err));
+                if recipients.len() == 1 {
+                    RecipientDelivery::Failed(error)
+                } else {
+                    RecipientDelivery::Unconfirmed(error)
+                }
+            }
+        };
+        recipients
+            .iter()
+            .map(|recipient| (recipient.clone(), delivery.clone()))
+            .collect()
+    }
 }
 
 pub type UploadEventCallback = dyn Fn(UploadEvent) + 'static;
@@ -140,8 +615,20 @@ pub struct ShareBuilder<'a> {
     recipients: Vec<String>,
     security: ShareSecurityOptions,
 
-    files: Vec<PathBuf>,
+    files: Vec<UploadFile>,
+    streams: Vec<UploadStream>,
     event_callback: Box<UploadEventCallback>,
+    retry_policy: RetryPolicy,
+    parallel_uploads: usize,
+
+    journal_directory: Option<PathBuf>,
+    journal: Option<UploadJournal>,
+
+    /// Upload the files to the existing share with the given id instead of creating one
+    existing_share: bool,
+
+    /// Ids of files to remove from the existing share once the new files have been uploaded
+    removed_files: Vec<String>,
 }
 
 impl<'a> ShareBuilder<'a> {
@@ -160,30 +647,78 @@ impl<'a> ShareBuilder<'a> {
         self
     }
 
-    #[allow(unused)]
     pub fn set_expiration(&mut self, expiration: ExpireDuration) -> &mut Self {
         self.expiration = expiration;
         self
     }
 
-    #[allow(unused)]
     pub fn set_security_options(&mut self, security: ShareSecurityOptions) -> &mut Self {
         self.security = security;
         self
     }
 
+    /// Add a recipient which will be notified by email once the share has been completed
+    pub fn add_recipient(&mut self, recipient: String) -> &mut Self {
+        self.recipients.push(recipient);
+        self
+    }
+
+    /// Remove a file from the existing share after the new files have been uploaded.
+    /// The file will be kept if any upload fails.
+    pub fn remove_file(&mut self, file_id: String) -> &mut Self {
+        self.removed_files.push(file_id);
+        self
+    }
+
+    #[allow(unused)]
     pub fn add_file(&mut self, file: PathBuf) -> &mut Self {
+        let name = file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        self.add_upload_file(UploadFile { path: file, name })
+    }
+
+    /// Add a file which will be uploaded with the given name
+    pub fn add_upload_file(&mut self, file: UploadFile) -> &mut Self {
         self.files.push(file);
         self
     }
 
+    /// Add a stream which will be uploaded after all files.
+    /// Shares containing streams can not be resumed, hence no journal will be recorded.
+    pub fn add_stream(&mut self, stream: UploadStream) -> &mut Self {
+        self.streams.push(stream);
+        self
+    }
+
     pub fn with_callback(&mut self, callback: impl Fn(UploadEvent) + 'static) -> &mut Self {
         self.event_callback = Box::new(callback);
         self
     }
 
+    pub fn set_retry_policy(&mut self, retry_policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Set how many files will be uploaded at once.
+    /// Chunks of a single file are always uploaded in order, as the server expects them sequentially.
+    pub fn set_parallel_uploads(&mut self, parallel_uploads: usize) -> &mut Self {
+        self.parallel_uploads = parallel_uploads.max(1);
+        self
+    }
+
+    /// Record the upload progress within a journal in the given directory,
+    /// so the upload can be resumed if it gets interrupted.
+    pub fn set_journal_directory(&mut self, directory: PathBuf) -> &mut Self {
+        self.journal_directory = Some(directory);
+        self
+    }
+
     /* TODO: Some kind of process monitor */
-    pub async fn upload(self) -> anyhow::Result<String> {
+    pub async fn upload(mut self) -> anyhow::Result<UploadedShare> {
         let share_config = self.api.public_config().await?;
 
         let chunk_size = share_config
@@ -194,50 +729,315 @@ impl<'a> ShareBuilder<'a> {
 
         log::debug!("Uploading files using a chunk size of {} bytes", chunk_size);
 
-        let share_id = self.create_share().await?;
-        (*self.event_callback)(UploadEvent::ShareCreated {
-            share_id: share_id.clone(),
-        });
+        if !self.recipients.is_empty()
+            && share_config.get_bool("email.enableShareEmailRecipients") != Some(true)
+        {
+            anyhow::bail!("the server does not allow sending shares to email recipients");
+        }
 
-        let mut progress = UploadProgress::default();
-        progress.files_total = self.files.len() as u64;
-        progress.file_length = self
-            .files
-            .iter()
-            .filter_map(|file| file.metadata().ok())
-            .map(|meta| meta.len())
-            .sum();
+        let mut journal = self.journal.take();
+        let share_id = match &journal {
+            Some(journal) => {
+                if journal.state().chunk_size != chunk_size as u64 {
+                    anyhow::bail!(
+                        "the servers chunk size changed since the upload has been started"
+                    );
+                }
 
-        for file in self.files.iter() {
-            progress.file_current = file.clone();
-            (*self.event_callback)(UploadEvent::UploadProgress(progress.clone()));
+                let share_id = journal.state().share_id.clone();
+                if journal.state().existing_share {
+                    self.reopen_share(&share_id).await?;
+                }
+                share_id
+            }
+            None if self.existing_share => {
+                let share_id = self.id.clone().context("missing id of the share")?;
+                self.reopen_share(&share_id).await?;
+                share_id
+            }
+            None => self.create_share().await?,
+        };
 
-            let result = self
-                .upload_file(&share_id, file, chunk_size, &mut progress)
-                .await;
+        let streams = std::mem::take(&mut self.streams);
+        if !streams.is_empty() {
+            log::debug!("Skipping the upload journal as streams can not be resumed");
+        } else if let (None, Some(directory)) = (&journal, &self.journal_directory) {
+            match UploadJournal::create(
+                directory,
+                &self.api.base_url,
+                &share_id,
+                chunk_size as u64,
+                &self.files,
+                self.existing_share,
+            ) {
+                Ok(value) => journal = Some(value),
+                Err(err) => log::warn!("Failed to create upload journal: {:#}", err),
+            }
+        }
+
+        if self.existing_share {
+            (*self.event_callback)(UploadEvent::ShareReopened {
+                share_id: share_id.clone(),
+            });
+        } else {
+            (*self.event_callback)(UploadEvent::ShareCreated {
+                share_id: share_id.clone(),
+            });
+        }
+
+        let progress = RefCell::new(UploadProgress {
+            files_total: (self.files.len() + streams.len()) as u64,
+            bytes_total: self
+                .files
+                .iter()
+                .filter_map(|file| file.path.metadata().ok())
+                .map(|meta| meta.len())
+                .sum(),
+            ..Default::default()
+        });
+        let journal = RefCell::new(journal);
 
-            match result {
-                Ok(_file_id) => {
-                    progress.files_uploaded += 1;
+        let mut files = futures::stream::iter(self.files.iter().enumerate())
+            .map(|(file_index, file)| {
+                self.upload_entry(&share_id, chunk_size, file_index, file, &journal, &progress)
+            })
+            .buffer_unordered(self.parallel_uploads)
+            .filter_map(futures::future::ready)
+            .collect::<Vec<_>>()
+            .await;
+
+        for (stream_index, stream) in streams.into_iter().enumerate() {
+            let file_index = self.files.len() + stream_index;
+            files.extend(
+                self.upload_stream_entry(&share_id, chunk_size, file_index, stream, &progress)
+                    .await,
+            );
+        }
+
+        let progress = progress.into_inner();
+        let journal = journal.into_inner();
+        if progress.files_failed > 0 && !self.removed_files.is_empty() {
+            /* keep the files which should have been replaced and restore the completed share */
+            if let Some(journal) = journal {
+                if let Err(err) = journal.remove() {
+                    log::warn!("Failed to remove upload journal: {:#}", err);
                 }
-                Err(err) => {
-                    progress.files_failed += 1;
+            }
+            self.complete_share(&share_id)
+                .await
+                .with_context(|| format!("complete share {}", share_id))?;
+            anyhow::bail!(
+                "{} file(s) failed to upload, the files of the share have been kept",
+                progress.files_failed
+            );
+        }
 
-                    log::error!("Failed to upload {}: {}", file.display(), err);
-                    (*self.event_callback)(UploadEvent::UploadError {
-                        file: file.clone(),
-                        error: Arc::new(err),
-                    });
+        if let Some(mut journal) = journal {
+            if progress.files_failed > 0 {
+                if self.existing_share {
+                    /* never leave a share offline which has been completed before */
+                    if let Err(err) = journal.retain_incomplete() {
+                        log::warn!("Failed to update upload journal: {:#}", err);
+                    }
+                    self.complete_share(&share_id)
+                        .await
+                        .with_context(|| format!("complete share {}", share_id))?;
                 }
+
+                /* keep the journal, so the failed files can be uploaded later on */
+                anyhow::bail!(
+                    "{} file(s) failed to upload.\nRun `pingvin-cli resume {}` to continue the upload.",
+                    progress.files_failed,
+                    share_id
+                );
+            }
+
+            if let Err(err) = journal.remove() {
+                log::warn!("Failed to remove upload journal: {:#}", err);
             }
-            (*self.event_callback)(UploadEvent::UploadProgress(progress.clone()));
         }
 
-        if let Err(err) = self.complete_share(&share_id).await {
+        /* complete the share even if removing files failed, so it never stays reopened */
+        let mut remove_errors = vec![];
+        for file_id in &self.removed_files {
+            if let Err(err) = self.api.delete_share_file(&share_id, file_id).await {
+                remove_errors.push(format!("remove file {}: {:#}", file_id, err));
+            }
+        }
+
+        let completed = self.complete_share(&share_id).await;
+        if let Err(err) = &completed {
             log::warn!("Failed to mark share {} as completed: {}", share_id, err);
         }
+        if !remove_errors.is_empty() {
+            anyhow::bail!(remove_errors.join("\n"));
+        }
+
+        for (recipient, delivery) in
+            RecipientDelivery::from_completion(&self.recipients, &completed)
+        {
+            (*self.event_callback)(UploadEvent::RecipientNotified {
+                recipient,
+                delivery,
+            });
+        }
         (*self.event_callback)(UploadEvent::ShareCompleted);
-        Ok(share_id)
+        Ok(UploadedShare {
+            id: share_id,
+            files,
+        })
+    }
+
+    /// Upload a single file of the share and track the result within the progress and journal.
+    /// Returns the uploaded file, if the upload succeeded.
+    async fn upload_entry(
+        &self,
+        share_id: &str,
+        chunk_size: usize,
+        file_index: usize,
+        upload: &UploadFile,
+        journal: &RefCell<Option<UploadJournal>>,
+        progress: &RefCell<UploadProgress>,
+    ) -> Option<UploadedFile> {
+        let file = upload.path.as_path();
+        let journal_file = journal
+            .borrow()
+            .as_ref()
+            .map(|journal| journal.state().files[file_index].clone());
+
+        if journal_file.as_ref().is_some_and(|entry| entry.completed) {
+            log::debug!(
+                "Skipping {} as it has already been uploaded",
+                file.display()
+            );
+
+            let entry = journal_file?;
+            let mut progress = progress.borrow_mut();
+            progress.file_started(file_index, file, entry.length);
+            progress.file_finished(file_index, true);
+            return entry.file_id.map(|id| UploadedFile { id });
+        }
+
+        let file_length = file.metadata().map(|meta| meta.len()).unwrap_or(0);
+        progress
+            .borrow_mut()
+            .file_started(file_index, file, file_length);
+        self.emit_progress(progress);
+
+        let (file_id, chunk_index) = match &journal_file {
+            Some(entry) => (
+                entry.file_id.clone(),
+                entry.chunk_index.map_or(0, |index| index as usize + 1),
+            ),
+            None => (None, 0),
+        };
+
+        let file_modified = match &journal_file {
+            Some(entry) if entry.chunk_index.is_some() => file_length != entry.length,
+            _ => false,
+        };
+
+        let result = if file_modified {
+            Err(anyhow::anyhow!(
+                "file has been modified since the upload has been started"
+            ))
+        } else {
+            self.upload_file(
+                share_id,
+                file_index,
+                upload,
+                chunk_size,
+                progress,
+                file_id,
+                chunk_index,
+                |file_id, chunk_index| {
+                    let mut journal = journal.borrow_mut();
+                    let Some(journal) = journal.as_mut() else {
+                        return;
+                    };
+
+                    if let Err(err) = journal.record_chunk(file_index, file_id, chunk_index as u64)
+                    {
+                        log::warn!("Failed to update upload journal: {:#}", err);
+                    }
+                },
+            )
+            .await
+        };
+
+        let uploaded = match result {
+            Ok(file_id) => {
+                progress.borrow_mut().file_finished(file_index, true);
+
+                if let Some(journal) = journal.borrow_mut().as_mut() {
+                    if let Err(err) = journal.record_completed(file_index) {
+                        log::warn!("Failed to update upload journal: {:#}", err);
+                    }
+                }
+                Some(UploadedFile { id: file_id })
+            }
+            Err(err) => {
+                progress.borrow_mut().file_finished(file_index, false);
+                (*self.event_callback)(UploadEvent::UploadError {
+                    file: file.to_owned(),
+                    error: Arc::new(err),
+                });
+                None
+            }
+        };
+        self.emit_progress(progress);
+        uploaded
+    }
+
+    /// Upload a stream of unknown length as a single file of the share
+    async fn upload_stream_entry(
+        &self,
+        share_id: &str,
+        chunk_size: usize,
+        file_index: usize,
+        mut stream: UploadStream,
+        progress: &RefCell<UploadProgress>,
+    ) -> Option<UploadedFile> {
+        let file = PathBuf::from(&stream.name);
+        progress.borrow_mut().file_started(file_index, &file, 0);
+        self.emit_progress(progress);
+
+        let mut source = ChunkSource::stream(&mut *stream.reader, chunk_size);
+        let result = self
+            .upload_chunks(
+                share_id,
+                file_index,
+                &file,
+                &stream.name,
+                &mut source,
+                progress,
+                None,
+                0,
+                |_, _| {},
+            )
+            .await;
+
+        let uploaded = match result {
+            Ok(file_id) => {
+                progress.borrow_mut().file_finished(file_index, true);
+                Some(UploadedFile { id: file_id })
+            }
+            Err(err) => {
+                progress.borrow_mut().file_finished(file_index, false);
+                (*self.event_callback)(UploadEvent::UploadError {
+                    file,
+                    error: Arc::new(err),
+                });
+                None
+            }
+        };
+        self.emit_progress(progress);
+        uploaded
+    }
+
+    fn emit_progress(&self, progress: &RefCell<UploadProgress>) {
+        let progress = progress.borrow().clone();
+        (*self.event_callback)(UploadEvent::UploadProgress(progress));
     }
 
     async fn create_share(&self) -> anyhow::Result<String> {
@@ -288,19 +1088,59 @@ impl<'a> ShareBuilder<'a> {
             })
             .headers(self.api.authentication_headers.clone())
             .send()
-            .await?
-            .error_for_status()?;
+            .await?;
 
-        let response: Response = response.json().await?;
+        /* the public config has no limits for share security, report the server validation instead */
+        let response: Response = error_for_status_message(response).await?.json().await?;
         Ok(response.id)
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn upload_file(
         &self,
         share_id: &str,
-        file_path: &Path,
+        file_index: usize,
+        upload: &UploadFile,
         chunk_size: usize,
-        progress: &mut UploadProgress,
+        progress: &RefCell<UploadProgress>,
+        file_id: Option<String>,
+        current_chunk_index: usize,
+        on_chunk_acknowledged: impl FnMut(&str, usize),
+    ) -> anyhow::Result<String> {
+        let file = File::options()
+            .read(true)
+            .open(&upload.path)
+            .await
+            .context("open")?;
+
+        let file_length = file.metadata().await?.len() as usize;
+        let mut source = ChunkSource::file(file, file_length, chunk_size);
+        self.upload_chunks(
+            share_id,
+            file_index,
+            &upload.path,
+            &upload.name,
+            &mut source,
+            progress,
+            file_id,
+            current_chunk_index,
+            on_chunk_acknowledged,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn upload_chunks(
+        &self,
+        share_id: &str,
+        file_index: usize,
+        file_path: &Path,
+        file_name: &str,
+        source: &mut ChunkSource<'_>,
+        progress: &RefCell<UploadProgress>,
+        mut file_id: Option<String>,
+        mut current_chunk_index: usize,
+        mut on_chunk_acknowledged: impl FnMut(&str, usize),
     ) -> anyhow::Result<String> {
         #[derive(Default, Debug, Deserialize)]
         #[serde(rename_all = "camelCase")]
@@ -313,112 +1153,187 @@ impl<'a> ShareBuilder<'a> {
             .base_url
             .join(&format!("shares/{}/files", share_id))?;
 
-        let file = File::options()
-            .read(true)
-            .open(&file_path)
-            .await
-            .context("open")?;
-
-        let file_length = file.metadata().await?.len() as usize;
-        let file_name = file_path
-            .file_name()
-            .context("expected a file name")?
-            .to_string_lossy();
-
-        let chunk_count = {
-            let mut chunks = (file_length / chunk_size).max(1);
-            if chunks * chunk_size < file_length {
-                chunks += 1;
-            }
-            chunks
-        };
-        let mut current_chunk_index = 0;
+        if file_name.is_empty() {
+            anyhow::bail!("expected a file name");
+        }
 
-        let bytes_uploaded = Arc::new(AtomicU64::new(0));
-        progress.file_length = file_length as u64;
+        let chunk_size = source.chunk_size();
+        let bytes_uploaded = Arc::new(AtomicU64::new(
+            (current_chunk_index * chunk_size).min(source.length().unwrap_or(usize::MAX)) as u64,
+        ));
 
-        let mut file_id: Option<String> = None;
-        while current_chunk_index < chunk_count {
+        let mut realigned_chunk_index = None;
+        loop {
+            let chunk = source.chunk(current_chunk_index).await?;
             let debug_info = format!(
                 "chunk {}/{} ({}/{} bytes)",
-                current_chunk_index,
-                chunk_count,
-                current_chunk_index * chunk_size,
-                file_length
+                chunk.index,
+                chunk.total,
+                chunk.offset,
+                source
+                    .length()
+                    .map_or_else(|| "?".to_string(), |length| length.to_string())
             );
 
-            let current_chunk_length = {
-                let chunk_base = current_chunk_index * chunk_size;
-                if chunk_base > file_length {
-                    0
-                } else {
-                    (file_length - chunk_base).min(chunk_size)
-                }
-            };
-
-            let file_chunk = {
-                let mut chunk = file.try_clone().await?;
-                chunk
-                    .seek(SeekFrom::Start((current_chunk_index * chunk_size) as u64))
-                    .await
-                    .with_context(|| format!("seek to chunk {}", debug_info))?;
-                chunk.take(chunk_size as u64)
-            };
-
             let mut query: Vec<(&str, Cow<'_, str>)> = Vec::with_capacity(4);
             if let Some(id) = &file_id {
                 query.push(("id", id.into()));
             }
-            query.push(("name", file_name.clone()));
-            query.push(("chunkIndex", format!("{}", current_chunk_index).into()));
-            query.push(("totalChunks", format!("{}", chunk_count).into()));
-
-            let body_stream = FramedRead::new(file_chunk, BytesCodec::new());
-            let body_stream = body_stream.map({
-                let bytes_uploaded = bytes_uploaded.clone();
-                move |chunk| {
-                    if let Ok(chunk) = &chunk {
-                        bytes_uploaded.fetch_add(chunk.len() as u64, Ordering::Relaxed);
-                    }
+            query.push(("name", file_name.into()));
+            query.push(("chunkIndex", format!("{}", chunk.index).into()));
+            query.push(("totalChunks", format!("{}", chunk.total).into()));
 
-                    chunk
-                }
-            });
-
-            log::debug!("Uploading {}", debug_info);
-            let mut upload = self
-                .api
-                .http_client
-                .post(url.clone())
-                .body(Body::wrap_stream(body_stream))
-                .header("Content-Type", "application/octet-stream")
-                .header("Content-Length", format!("{}", current_chunk_length))
-                .query(&query)
-                .headers(self.api.authentication_headers.clone())
-                .send();
-
-            let mut stats_poll = time::interval(Duration::from_millis(500));
+            let mut attempt = 1;
             let response = loop {
-                tokio::select! {
-                    _ = stats_poll.tick() => {
-                        progress.file_bytes_uploaded = bytes_uploaded.load(Ordering::Relaxed);
-                        (*self.event_callback)(UploadEvent::UploadProgress(progress.clone()));
-                    },
-                    result = &mut upload => break result?,
+                let result = async {
+                    let body_stream = source.body(&chunk).await?.map({
+                        let bytes_uploaded = bytes_uploaded.clone();
+                        move |chunk| {
+                            if let Ok(chunk) = &chunk {
+                                bytes_uploaded.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+                            }
+
+                            chunk
+                        }
+                    });
+
+                    log::debug!("Uploading {}", debug_info);
+                    let mut upload = self
+                        .api
+                        .http_client
+                        .post(url.clone())
+                        .body(Body::wrap_stream(body_stream))
+                        .header("Content-Type", "application/octet-stream")
+                        .header("Content-Length", format!("{}", chunk.length))
+                        .query(&query)
+                        .headers(self.api.authentication_headers.clone())
+                        .send();
+
+                    let mut stats_poll = time::interval(Duration::from_millis(500));
+                    let mut stall_detector = StallDetector::new(
+                        self.retry_policy.idle_timeout,
+                        bytes_uploaded.load(Ordering::Relaxed),
+                        Instant::now(),
+                    );
+                    let response = loop {
+                        tokio::select! {
+                            _ = stats_poll.tick() => {
+                                let bytes = bytes_uploaded.load(Ordering::Relaxed);
+                                progress.borrow_mut().file_progress(file_index, bytes);
+                                self.emit_progress(progress);
+
+                                if stall_detector.is_stalled(bytes, Instant::now()) {
+                                    return Err(anyhow::Error::new(std::io::Error::new(
+                                        std::io::ErrorKind::TimedOut,
+                                        format!("no progress within {:?}", self.retry_policy.idle_timeout),
+                                    )));
+                                }
+                            },
+                            result = &mut upload => break result?,
+                        }
+                    };
+
+                    /* server errors may be transient, let the retry policy decide */
+                    if response.status().is_server_error() {
+                        response.error_for_status_ref()?;
+                    }
+                    Ok::<_, anyhow::Error>(response)
+                }
+                .await
+                .with_context(|| format!("upload {}", debug_info));
+
+                match result {
+                    Ok(response) => break response,
+                    Err(err) if self.retry_policy.should_retry(attempt, &err) => {
+                        attempt += 1;
+                        let delay = self.retry_policy.delay(attempt);
+                        /* the retry is reported by the event handler */
+                        log::debug!(
+                            "Failed to upload {} of {}: {:#}. Retrying in {:?} (attempt {}/{})",
+                            debug_info,
+                            file_path.display(),
+                            err,
+                            delay,
+                            attempt,
+                            self.retry_policy.max_attempts
+                        );
+
+                        (*self.event_callback)(UploadEvent::UploadRetry {
+                            file: file_path.to_owned(),
+                            chunk_index: chunk.index,
+                            chunk_count: chunk.total,
+                            attempt,
+                            error: Arc::new(err),
+                        });
+
+                        bytes_uploaded.store(chunk.offset as u64, Ordering::Relaxed);
+                        time::sleep(delay).await;
+                    }
+                    Err(err) => return Err(err),
                 }
             };
+
+            if response.status() == StatusCode::BAD_REQUEST && file_id.is_some() {
+                let payload = response
+                    .json::<serde_json::Value>()
+                    .await
+                    .unwrap_or_default();
+                let expected_chunk_index = payload
+                    .get("expectedChunkIndex")
+                    .and_then(serde_json::Value::as_u64)
+                    .map(|index| index as usize);
+
+                match expected_chunk_index {
+                    /* the server already received more (or less) chunks than we know of */
+                    Some(index) if index < chunk.total && realigned_chunk_index != Some(index) => {
+                        log::debug!("Server expected chunk {} instead of {}", index, debug_info);
+
+                        realigned_chunk_index = Some(index);
+                        current_chunk_index = index;
+                        bytes_uploaded.store((index * chunk_size) as u64, Ordering::Relaxed);
+                        continue;
+                    }
+                    _ => anyhow::bail!("server rejected {}: {}", debug_info, payload),
+                }
+            }
+
             let response = response.error_for_status()?.json::<Response>().await?;
+            on_chunk_acknowledged(&response.id, chunk.index);
 
             file_id = Some(response.id);
-            current_chunk_index += 1;
+            current_chunk_index = chunk.index + 1;
 
-            progress.file_bytes_uploaded = bytes_uploaded.load(Ordering::Relaxed);
-            (*self.event_callback)(UploadEvent::UploadProgress(progress.clone()));
+            progress
+                .borrow_mut()
+                .file_progress(file_index, bytes_uploaded.load(Ordering::Relaxed));
+            self.emit_progress(progress);
+
+            if current_chunk_index >= chunk.total {
+                break;
+            }
         }
 
         Ok(file_id.context("failed to obtain a file id")?)
     }
 
+    /// Revert the completion of the share, so files can be uploaded again
+    async fn reopen_share(&self, share_id: &str) -> anyhow::Result<()> {
+        let url = self
+            .api
+            .base_url
+            .join(&format!("shares/{}/complete", share_id))?;
+
+        self.api
+            .http_client
+            .delete(url)
+            .headers(self.api.authentication_headers.clone())
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("reopen share {}", share_id))?;
+        Ok(())
+    }
+
     async fn complete_share(&self, share_id: &str) -> anyhow::Result<()> {
         #[derive(Default, Debug, Serialize)]
         struct Payload<'a> {
@@ -443,3 +1358,722 @@ impl<'a> ShareBuilder<'a> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, fs, rc::Rc, time::Duration};
+
+    use super::{
+        mock::MockServer, LoginResult, PingvinApi, RecipientDelivery, RetryPolicy, Session,
+        UploadEvent, UploadFile, UploadJournal, UploadStream,
+    };
+
+    #[tokio::test]
+    async fn test_session_refresh() {
+        let server = MockServer::start(16).await;
+        server
+            .state()
+            .users
+            .insert("alice".to_string(), "secret".to_string());
+
+        let mut api = PingvinApi::new(server.base_url()).unwrap();
+        assert_eq!(
+            api.login("alice", "wrong").await.unwrap(),
+            LoginResult::Rejected
+        );
+        assert_eq!(
+            api.login("alice", "secret").await.unwrap(),
+            LoginResult::LoggedIn
+        );
+        assert!(api.validate_session().await.unwrap());
+
+        let session = api.session().unwrap().clone();
+        assert_eq!(session.refresh_token.as_deref(), Some("refresh-0"));
+
+        /* the access token expired, but the refresh token is still valid */
+        server.state().access_tokens.clear();
+        let mut api = PingvinApi::new(server.base_url()).unwrap();
+        api.set_session(session.clone()).unwrap();
+        assert!(!api.validate_session().await.unwrap());
+        assert!(api.refresh_session().await.unwrap());
+        assert!(api.validate_session().await.unwrap());
+        assert_eq!(api.session().unwrap().refresh_token, session.refresh_token);
+
+        /* the refresh token has been revoked as well */
+        server.state().refresh_tokens.clear();
+        let mut api = PingvinApi::new(server.base_url()).unwrap();
+        api.set_session(Session {
+            access_token: "expired".to_string(),
+            refresh_token: session.refresh_token,
+        })
+        .unwrap();
+        assert!(!api.refresh_session().await.unwrap());
+        assert_eq!(server.state().refresh_requests, 2);
+    }
+
+    #[tokio::test]
+    async fn test_totp_login() {
+        let server = MockServer::start(16).await;
+        {
+            let mut state = server.state();
+            state.users.insert("bob".to_string(), "secret".to_string());
+            state
+                .totp_users
+                .insert("bob".to_string(), "123456".to_string());
+        }
+
+        let mut api = PingvinApi::new(server.base_url()).unwrap();
+        let LoginResult::TotpRequired { login_token } = api.login("bob", "secret").await.unwrap()
+        else {
+            panic!("expected a totp challenge");
+        };
+        assert!(api.session().is_none());
+
+        assert_eq!(
+            api.login_totp(&login_token, "000000").await.unwrap(),
+            LoginResult::Rejected
+        );
+        assert_eq!(
+            api.login_totp(&login_token, "123456").await.unwrap(),
+            LoginResult::LoggedIn
+        );
+        assert!(api.validate_session().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_resume_interrupted_upload() {
+        let server = MockServer::start(16).await;
+        let directory = tempfile::tempdir().unwrap();
+        let journal_directory = directory.path().join("journal");
+
+        let file_a = directory.path().join("a.bin");
+        let file_b = directory.path().join("b.bin");
+        fs::write(&file_a, (0..40u8).collect::<Vec<_>>()).unwrap();
+        fs::write(&file_b, (0..100u8).collect::<Vec<_>>()).unwrap();
+
+        /* a.bin takes 3 requests, drop the connection while uploading the 4th chunk of b.bin */
+        server.state().drop_chunk_requests = vec![6];
+
+        let api = PingvinApi::new(server.base_url()).unwrap();
+        let mut builder = api.create_share();
+        builder
+            .set_id("resume".to_string())
+            .add_file(file_a.clone())
+            .add_file(file_b.clone())
+            .set_journal_directory(journal_directory.clone())
+            .set_retry_policy(RetryPolicy::disabled());
+        assert!(builder.upload().await.is_err());
+
+        {
+            let state = server.state();
+            let share = &state.shares["resume"];
+            assert!(!share.completed);
+            assert!(share.files[0].completed);
+            assert_eq!(share.files[1].chunks_received, 3);
+        }
+
+        let journal = UploadJournal::find(&journal_directory, "resume").unwrap();
+        assert!(journal.state().files[0].completed);
+        assert_eq!(journal.state().files[1].chunk_index, Some(2));
+
+        let share = api.resume_share(journal).upload().await.unwrap();
+        assert_eq!(share.id, "resume");
+        assert_eq!(share.files.len(), 2);
+
+        let state = server.state();
+        let share = &state.shares["resume"];
+        assert!(share.completed);
+        assert_eq!(share.files.len(), 2);
+        assert_eq!(share.files[0].data, fs::read(&file_a).unwrap());
+        assert_eq!(share.files[1].data, fs::read(&file_b).unwrap());
+        assert!(share.files[1].completed);
+        assert!(UploadJournal::list(&journal_directory).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_append_failure_completes_share() {
+        let server = MockServer::start(16).await;
+        server
+            .state()
+            .users
+            .insert("alice".to_string(), "secret".to_string());
+
+        let directory = tempfile::tempdir().unwrap();
+        let journal_directory = directory.path().join("journal");
+        let first = directory.path().join("first.bin");
+        let second = directory.path().join("second.bin");
+        let third = directory.path().join("third.bin");
+        fs::write(&first, [1u8; 10]).unwrap();
+        fs::write(&second, [2u8; 10]).unwrap();
+        fs::write(&third, [3u8; 40]).unwrap();
+
+        let mut api = PingvinApi::new(server.base_url()).unwrap();
+        api.login("alice", "secret").await.unwrap();
+
+        let mut builder = api.create_share();
+        builder.set_id("album".to_string()).add_file(first);
+        builder.upload().await.unwrap();
+
+        /* second.bin succeeds, the second chunk of third.bin fails */
+        server.state().reject_chunk_requests = vec![(3, 413)];
+        let mut builder = api.append_to_share("album".to_string());
+        builder
+            .add_file(second)
+            .add_file(third.clone())
+            .set_journal_directory(journal_directory.clone())
+            .set_retry_policy(fast_retry_policy());
+        assert!(builder.upload().await.is_err());
+        assert!(server.state().shares["album"].completed);
+
+        let journal = UploadJournal::find(&journal_directory, "album").unwrap();
+        assert!(journal.state().existing_share);
+        assert_eq!(journal.state().files.len(), 1);
+        assert_eq!(journal.state().files[0].upload_name(), "third.bin");
+
+        api.resume_share(journal).upload().await.unwrap();
+        let state = server.state();
+        let share = &state.shares["album"];
+        assert!(share.completed);
+        let uploaded = share
+            .files
+            .iter()
+            .rev()
+            .find(|file| file.name == "third.bin");
+        assert_eq!(uploaded.unwrap().data, [3u8; 40]);
+        assert!(UploadJournal::list(&journal_directory).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_resume_realigns_chunk_index() {
+        let server = MockServer::start(16).await;
+        let directory = tempfile::tempdir().unwrap();
+        let journal_directory = directory.path().join("journal");
+
+        let file = directory.path().join("file.bin");
+        fs::write(&file, (0..64u8).collect::<Vec<_>>()).unwrap();
+
+        server.state().drop_chunk_requests = vec![2];
+
+        let api = PingvinApi::new(server.base_url()).unwrap();
+        let mut builder = api.create_share();
+        builder
+            .set_id("realign".to_string())
+            .add_file(file.clone())
+            .set_journal_directory(journal_directory.clone())
+            .set_retry_policy(RetryPolicy::disabled());
+        assert!(builder.upload().await.is_err());
+
+        /* pretend the acknowledgement of the second chunk got lost */
+        let journal_path = journal_directory.join("realign.json");
+        let mut state: serde_json::Value =
+            serde_json::from_slice(&fs::read(&journal_path).unwrap()).unwrap();
+        state["files"][0]["chunkIndex"] = 0.into();
+        fs::write(&journal_path, state.to_string()).unwrap();
+
+        let journal = UploadJournal::load(&journal_path).unwrap();
+        api.resume_share(journal).upload().await.unwrap();
+
+        let state = server.state();
+        let share = &state.shares["realign"];
+        assert!(share.completed);
+        assert_eq!(share.files[0].data, fs::read(&file).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_upload_stream() {
+        let server = MockServer::start(16).await;
+        let directory = tempfile::tempdir().unwrap();
+        let journal_directory = directory.path().join("journal");
+        let data = (0..50u8).collect::<Vec<_>>();
+
+        let progress = Rc::new(RefCell::new(None));
+        let api = PingvinApi::new(server.base_url()).unwrap();
+        let mut builder = api.create_share();
+        builder
+            .set_id("stream".to_string())
+            .add_stream(UploadStream {
+                name: "archive.tar.gz".to_string(),
+                reader: Box::new(std::io::Cursor::new(data.clone())),
+            })
+            .set_journal_directory(journal_directory.clone())
+            .with_callback({
+                let progress = progress.clone();
+                move |event| {
+                    if let UploadEvent::UploadProgress(value) = event {
+                        *progress.borrow_mut() = Some(value);
+                    }
+                }
+            });
+        builder.upload().await.unwrap();
+
+        let state = server.state();
+        let share = &state.shares["stream"];
+        assert!(share.completed);
+        assert_eq!(share.files[0].name, "archive.tar.gz");
+        assert_eq!(share.files[0].data, data);
+        assert!(share.files[0].completed);
+        assert_eq!(share.files[0].chunks_received, 4);
+        assert!(!journal_directory.exists());
+
+        let progress = progress.borrow().clone().unwrap();
+        assert_eq!(progress.files_uploaded, 1);
+        assert_eq!(progress.bytes_total, 50);
+        assert_eq!(progress.bytes_uploaded, 50);
+    }
+
+    #[tokio::test]
+    async fn test_share_recipients() {
+        let server = MockServer::start(16).await;
+        let api = PingvinApi::new(server.base_url()).unwrap();
+
+        let mut builder = api.create_share();
+        builder
+            .set_id("mail".to_string())
+            .add_recipient("alice@example.com".to_string());
+        assert!(builder.upload().await.is_err());
+        assert!(server.state().shares.is_empty());
+
+        server.state().email_recipients = true;
+        let notified = Rc::new(RefCell::new(Vec::new()));
+        let mut builder = api.create_share();
+        builder
+            .set_id("mail".to_string())
+            .add_recipient("alice@example.com".to_string())
+            .add_recipient("bob@example.com".to_string())
+            .with_callback({
+                let notified = notified.clone();
+                move |event| {
+                    if let UploadEvent::RecipientNotified {
+                        recipient,
+                        delivery,
+                    } = event
+                    {
+                        notified.borrow_mut().push((recipient, delivery));
+                    }
+                }
+            });
+        builder.upload().await.unwrap();
+
+        assert_eq!(
+            server.state().shares["mail"].recipients,
+            vec!["alice@example.com", "bob@example.com"]
+        );
+        assert!(matches!(
+            notified.borrow().as_slice(),
+            [
+                (alice, RecipientDelivery::Sent),
+                (bob, RecipientDelivery::Sent)
+            ] if alice == "alice@example.com" && bob == "bob@example.com"
+        ));
+
+        server.state().mail_failure = true;
+        notified.borrow_mut().clear();
+        let mut builder = api.create_share();
+        builder
+            .set_id("failed".to_string())
+            .add_recipient("alice@example.com".to_string())
+            .with_callback({
+                let notified = notified.clone();
+                move |event| {
+                    if let UploadEvent::RecipientNotified {
+                        recipient,
+                        delivery,
+                    } = event
+                    {
+                        notified.borrow_mut().push((recipient, delivery));
+                    }
+                }
+            });
+        builder.upload().await.unwrap();
+        assert!(matches!(
+            notified.borrow().as_slice(),
+            [(alice, RecipientDelivery::Failed(_))] if alice == "alice@example.com"
+        ));
+
+        /* the server does not report which of several recipients could not be notified */
+        notified.borrow_mut().clear();
+        let mut builder = api.create_share();
+        builder
+            .set_id("unconfirmed".to_string())
+            .add_recipient("alice@example.com".to_string())
+            .add_recipient("bob@example.com".to_string())
+            .with_callback({
+                let notified = notified.clone();
+                move |event| {
+                    if let UploadEvent::RecipientNotified {
+                        recipient,
+                        delivery,
+                    } = event
+                    {
+                        notified.borrow_mut().push((recipient, delivery));
+                    }
+                }
+            });
+        builder.upload().await.unwrap();
+        assert!(matches!(
+            notified.borrow().as_slice(),
+            [
+                (_, RecipientDelivery::Unconfirmed(_)),
+                (_, RecipientDelivery::Unconfirmed(_))
+            ]
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_list_shares() {
+        let server = MockServer::start(16).await;
+        server
+            .state()
+            .users
+            .insert("alice".to_string(), "secret".to_string());
+
+        let directory = tempfile::tempdir().unwrap();
+        let file = directory.path().join("file.bin");
+        fs::write(&file, [0u8; 40]).unwrap();
+
+        let mut api = PingvinApi::new(server.base_url()).unwrap();
+        assert!(api.list_shares().await.is_err());
+        api.login("alice", "secret").await.unwrap();
+
+        let mut builder = api.create_share();
+        builder
+            .set_id("listed".to_string())
+            .set_name("Listed".to_string())
+            .add_file(file);
+        builder.upload().await.unwrap();
+        server.state().shares.get_mut("listed").unwrap().views = 2;
+
+        let shares = api.list_shares().await.unwrap();
+        assert_eq!(shares.len(), 1);
+        assert_eq!(shares[0].id, "listed");
+        assert_eq!(shares[0].name.as_deref(), Some("Listed"));
+        assert_eq!(shares[0].views, 2);
+        assert_eq!(shares[0].files.len(), 1);
+        assert_eq!(shares[0].total_size(), 40);
+        assert!(shares[0].expires_at().is_none());
+        assert!(shares[0].created_at.is_some());
+
+        server.state().shares.get_mut("listed").unwrap().expiration =
+            "2000-01-01T00:00:00.000Z".to_string();
+        assert!(api.list_shares().await.unwrap().is_empty());
+
+        api.delete_share("listed").await.unwrap();
+        assert!(api.delete_share("listed").await.is_err());
+        assert!(api.list_shares().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_append_to_share() {
+        let server = MockServer::start(16).await;
+        server
+            .state()
+            .users
+            .insert("alice".to_string(), "secret".to_string());
+
+        let directory = tempfile::tempdir().unwrap();
+        let first = directory.path().join("first.bin");
+        let second = directory.path().join("second.bin");
+        fs::write(&first, [1u8; 20]).unwrap();
+        fs::write(&second, [2u8; 30]).unwrap();
+
+        let mut api = PingvinApi::new(server.base_url()).unwrap();
+        api.login("alice", "secret").await.unwrap();
+
+        let mut builder = api.create_share();
+        builder.set_id("drop".to_string()).add_file(first);
+        builder.upload().await.unwrap();
+        assert!(server.state().shares["drop"].completed);
+
+        let reopened = Rc::new(RefCell::new(false));
+        let mut builder = api.append_to_share("drop".to_string());
+        builder.add_file(second).with_callback({
+            let reopened = reopened.clone();
+            move |event| {
+                if let UploadEvent::ShareReopened { share_id } = event {
+                    *reopened.borrow_mut() = share_id == "drop";
+                }
+            }
+        });
+        assert_eq!(builder.upload().await.unwrap().id, "drop");
+        assert!(*reopened.borrow());
+
+        {
+            let state = server.state();
+            let share = &state.shares["drop"];
+            assert!(share.completed);
+            assert_eq!(share.files.len(), 2);
+            assert_eq!(share.files[1].name, "second.bin");
+            assert_eq!(share.files[1].data, [2u8; 30]);
+        }
+
+        let builder = api.append_to_share("missing".to_string());
+        assert!(builder.upload().await.is_err());
+
+        /* replace the first file */
+        let replacement = directory.path().join("replacement.bin");
+        fs::write(&replacement, [3u8; 10]).unwrap();
+        let mut builder = api.append_to_share("drop".to_string());
+        builder
+            .remove_file("file-0".to_string())
+            .add_upload_file(UploadFile {
+                path: replacement,
+                name: "first.bin".to_string(),
+            });
+        builder.upload().await.unwrap();
+
+        let state = server.state();
+        let share = &state.shares["drop"];
+        assert!(share.completed);
+        assert_eq!(share.files.len(), 2);
+        assert_eq!(share.files[0].name, "second.bin");
+        assert_eq!(share.files[1].name, "first.bin");
+        assert_eq!(share.files[1].data, [3u8; 10]);
+    }
+
+    #[tokio::test]
+    async fn test_remove_failure_completes_share() {
+        let server = MockServer::start(16).await;
+        server
+            .state()
+            .users
+            .insert("alice".to_string(), "secret".to_string());
+
+        let directory = tempfile::tempdir().unwrap();
+        let first = directory.path().join("first.bin");
+        let second = directory.path().join("second.bin");
+        fs::write(&first, [1u8; 20]).unwrap();
+        fs::write(&second, [2u8; 20]).unwrap();
+
+        let mut api = PingvinApi::new(server.base_url()).unwrap();
+        api.login("alice", "secret").await.unwrap();
+
+        let mut builder = api.create_share();
+        builder
+            .set_id("notes".to_string())
+            .add_file(first)
+            .add_file(second);
+        builder.upload().await.unwrap();
+
+        server.state().delete_file_failure = true;
+        server.state().complete_requests = 0;
+        let mut builder = api.append_to_share("notes".to_string());
+        builder.remove_file("file-0".to_string());
+        assert!(builder.upload().await.is_err());
+
+        let state = server.state();
+        assert_eq!(state.complete_requests, 1);
+        assert!(state.shares["notes"].completed);
+        assert_eq!(state.shares["notes"].files.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_replace_failure_keeps_file() {
+        let server = MockServer::start(16).await;
+        server
+            .state()
+            .users
+            .insert("alice".to_string(), "secret".to_string());
+
+        let directory = tempfile::tempdir().unwrap();
+        let original = directory.path().join("report.pdf");
+        let replacement = directory.path().join("replacement.pdf");
+        fs::write(&original, [1u8; 20]).unwrap();
+        fs::write(&replacement, [2u8; 20]).unwrap();
+
+        let mut api = PingvinApi::new(server.base_url()).unwrap();
+        api.login("alice", "secret").await.unwrap();
+
+        let mut builder = api.create_share();
+        builder.set_id("report".to_string()).add_file(original);
+        builder.upload().await.unwrap();
+
+        server.state().reject_chunk_requests = vec![(2, 413)];
+        let mut builder = api.append_to_share("report".to_string());
+        builder
+            .remove_file("file-0".to_string())
+            .add_upload_file(UploadFile {
+                path: replacement,
+                name: "report.pdf".to_string(),
+            })
+            .set_retry_policy(fast_retry_policy());
+        assert!(builder.upload().await.is_err());
+
+        let state = server.state();
+        let share = &state.shares["report"];
+        assert!(share.completed);
+        assert_eq!(share.files[0].id, "file-0");
+        assert_eq!(share.files[0].data, [1u8; 20]);
+    }
+
+    fn fast_retry_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(20),
+            idle_timeout: Duration::from_secs(10),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_transient_failures() {
+        let server = MockServer::start(16).await;
+        let directory = tempfile::tempdir().unwrap();
+
+        let file = directory.path().join("file.bin");
+        fs::write(&file, (0..64u8).collect::<Vec<_>>()).unwrap();
+
+        {
+            let mut state = server.state();
+            state.drop_chunk_requests = vec![1];
+            state.reject_chunk_requests = vec![(3, 503), (4, 502)];
+        }
+
+        let retries = Rc::new(RefCell::new(vec![]));
+        let api = PingvinApi::new(server.base_url()).unwrap();
+        let mut builder = api.create_share();
+        builder
+            .set_id("retry".to_string())
+            .add_file(file.clone())
+            .set_retry_policy(fast_retry_policy())
+            .with_callback({
+                let retries = retries.clone();
+                move |event| {
+                    if let UploadEvent::UploadRetry {
+                        chunk_index,
+                        attempt,
+                        ..
+                    } = event
+                    {
+                        retries.borrow_mut().push((chunk_index, attempt));
+                    }
+                }
+            });
+        builder.upload().await.unwrap();
+
+        assert_eq!(*retries.borrow(), vec![(1, 2), (2, 2), (2, 3)]);
+
+        let state = server.state();
+        let share = &state.shares["retry"];
+        assert!(share.completed);
+        assert_eq!(share.files[0].data, fs::read(&file).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_share_rejected() {
+        let server = MockServer::start(16).await;
+        let api = PingvinApi::new(server.base_url()).unwrap();
+
+        let mut builder = api.create_share();
+        builder.set_id("short".to_string()).set_security_options(
+            serde_json::from_value(serde_json::json!({ "password": "ab" })).unwrap(),
+        );
+        let error = builder.upload().await.unwrap_err();
+        assert_eq!(
+            format!("{:#}", error),
+            "the server rejected the request (400 Bad Request): security.password must be longer than or equal to 3 characters"
+        );
+        assert!(server.state().shares.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retry_stalled_upload() {
+        let server = MockServer::start(16).await;
+        let directory = tempfile::tempdir().unwrap();
+
+        let file = directory.path().join("file.bin");
+        fs::write(&file, (0..32u8).collect::<Vec<_>>()).unwrap();
+        server.state().stall_chunk_requests = vec![1];
+
+        let api = PingvinApi::new(server.base_url()).unwrap();
+        let mut builder = api.create_share();
+        builder
+            .set_id("stalled".to_string())
+            .add_file(file.clone())
+            .set_retry_policy(RetryPolicy {
+                idle_timeout: Duration::from_millis(200),
+                ..fast_retry_policy()
+            });
+        builder.upload().await.unwrap();
+
+        let state = server.state();
+        assert_eq!(state.chunk_requests, 3);
+        assert!(state.shares["stalled"].completed);
+        assert_eq!(
+            state.shares["stalled"].files[0].data,
+            fs::read(&file).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_retry_permanent_failure() {
+        let server = MockServer::start(16).await;
+        let directory = tempfile::tempdir().unwrap();
+
+        let file = directory.path().join("file.bin");
+        fs::write(&file, (0..64u8).collect::<Vec<_>>()).unwrap();
+
+        server.state().reject_chunk_requests = vec![(1, 413)];
+
+        let api = PingvinApi::new(server.base_url()).unwrap();
+        let mut builder = api.create_share();
+        builder
+            .set_id("permanent".to_string())
+            .add_file(file.clone())
+            .set_retry_policy(fast_retry_policy());
+        builder.upload().await.unwrap();
+
+        /* the file should not have been retried */
+        let state = server.state();
+        assert_eq!(state.chunk_requests, 2);
+        assert_eq!(state.shares["permanent"].files[0].chunks_received, 1);
+    }
+
+    #[tokio::test]
+    async fn test_parallel_uploads() {
+        let server = MockServer::start(16).await;
+        let directory = tempfile::tempdir().unwrap();
+        server.state().chunk_delay = Some(Duration::from_millis(50));
+
+        let api = PingvinApi::new(server.base_url()).unwrap();
+        let mut builder = api.create_share();
+        builder
+            .set_id("parallel".to_string())
+            .set_parallel_uploads(3);
+
+        let mut files = vec![];
+        for index in 0..5u8 {
+            let file = directory.path().join(format!("{}.bin", index));
+            fs::write(&file, vec![index; 20 + index as usize * 10]).unwrap();
+            builder.add_file(file.clone());
+            files.push(file);
+        }
+
+        let last_progress = Rc::new(RefCell::new(None));
+        builder.with_callback({
+            let last_progress = last_progress.clone();
+            move |event| {
+                if let UploadEvent::UploadProgress(progress) = event {
+                    *last_progress.borrow_mut() = Some(progress);
+                }
+            }
+        });
+        builder.upload().await.unwrap();
+
+        let progress = last_progress.borrow_mut().take().unwrap();
+        assert_eq!(progress.files_uploaded, 5);
+        assert_eq!(progress.bytes_total, 200);
+        assert_eq!(progress.bytes_uploaded, 200);
+        assert!(progress.files_active.is_empty());
+
+        let state = server.state();
+        assert_eq!(state.max_active_chunk_requests, 3);
+
+        let share = &state.shares["parallel"];
+        assert!(share.completed);
+        for file in files {
+            let name = file.file_name().unwrap().to_string_lossy();
+            let uploaded = share.files.iter().find(|entry| entry.name == name).unwrap();
+            assert_eq!(uploaded.data, fs::read(&file).unwrap());
+        }
+    }
+}
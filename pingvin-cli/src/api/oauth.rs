@@ -0,0 +1,116 @@
+use reqwest::Url;
+
+use super::{Authenticator, LoginResult, PingvinApi};
+
+/// Opens the authorization URL, usually within the default browser
+pub type OpenBrowser = Box<dyn Fn(&Url) -> anyhow::Result<()>>;
+
+/// Reads the refresh token the user copied from the browser
+pub type ReadRefreshToken = Box<dyn Fn() -> anyhow::Result<String>>;
+
+/// Sign in using an OAuth provider configured on the server.
+///
+/// The server completes OAuth logins within the browser only: it stores the session
+/// within the `access_token` and `refresh_token` cookies of the web app and has no way
+/// to pass it to other clients. Hence the user copies the `refresh_token` cookie from
+/// the browser, which is exchanged for access tokens afterwards.
+pub struct OAuthAuthenticator {
+    pub provider: String,
+
+    pub open_browser: OpenBrowser,
+
+    pub read_refresh_token: ReadRefreshToken,
+}
+
+impl Authenticator for OAuthAuthenticator {
+    async fn authenticate(&self, server_api: &mut PingvinApi) -> anyhow::Result<LoginResult> {
+        let url = server_api
+            .base_url
+            .join(&format!("oauth/auth/{}", self.provider))?;
+        (self.open_browser)(&url)?;
+
+        let refresh_token = (self.read_refresh_token)()?;
+        let refresh_token = refresh_token.trim();
+        let refresh_token = refresh_token
+            .strip_prefix("refresh_token=")
+            .unwrap_or(refresh_token);
+        if refresh_token.is_empty() {
+            return Ok(LoginResult::Rejected);
+        }
+
+        server_api.login_refresh_token(refresh_token).await
+    }
+}
+
+impl PingvinApi {
+    /// OAuth providers users can sign in with
+    pub async fn oauth_providers(&self) -> anyhow::Result<Vec<String>> {
+        let response = self
+            .http_client
+            .get(self.base_url.join("oauth/available")?)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::OAuthAuthenticator;
+    use crate::api::{mock::MockServer, LoginResult, PingvinApi};
+
+    #[tokio::test]
+    async fn test_oauth_login() {
+        let server = MockServer::start(16).await;
+        {
+            let mut state = server.state();
+            state
+                .oauth_users
+                .insert("github".to_string(), "octocat".to_string());
+
+            /* the session the server stored within the cookies of the browser */
+            state.refresh_tokens.push("refresh-octocat".to_string());
+            state
+                .token_users
+                .insert("refresh-octocat".to_string(), "octocat".to_string());
+        }
+
+        let mut api = PingvinApi::new(server.base_url()).unwrap();
+        assert_eq!(api.oauth_providers().await.unwrap(), vec!["github"]);
+
+        let opened = Rc::new(RefCell::new(None));
+        let result = api
+            .authenticate(&OAuthAuthenticator {
+                provider: "github".to_string(),
+                open_browser: Box::new({
+                    let opened = opened.clone();
+                    move |url| {
+                        *opened.borrow_mut() = Some(url.path().to_string());
+                        Ok(())
+                    }
+                }),
+                read_refresh_token: Box::new(|| Ok("refresh_token=refresh-octocat\n".to_string())),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result, LoginResult::LoggedIn);
+        assert_eq!(opened.borrow().as_deref(), Some("/api/oauth/auth/github"));
+        assert!(api.session().unwrap().refresh_token.is_some());
+        assert_eq!(api.current_user().await.unwrap().username, "octocat");
+
+        let mut api = PingvinApi::new(server.base_url()).unwrap();
+        let result = api
+            .authenticate(&OAuthAuthenticator {
+                provider: "github".to_string(),
+                open_browser: Box::new(|_| Ok(())),
+                read_refresh_token: Box::new(|| Ok("invalid".to_string())),
+            })
+            .await
+            .unwrap();
+        assert_eq!(result, LoginResult::Rejected);
+        assert!(!api.is_authenticated());
+    }
+}
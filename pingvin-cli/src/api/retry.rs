@@ -0,0 +1,186 @@
+use std::{
+    io,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+use reqwest::StatusCode;
+
+/// Policy on how often and how fast failed chunk uploads will be retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum amount of attempts per chunk including the initial attempt
+    pub max_attempts: u32,
+
+    /// Delay before the first retry. The delay doubles with every further attempt.
+    pub base_delay: Duration,
+
+    /// Upper bound for the delay between two attempts
+    pub max_delay: Duration,
+
+    /// Time without any upload progress after which a chunk upload is considered stalled,
+    /// aborted and retried. This includes waiting for the response of the server.
+    pub idle_timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_millis(1_000),
+            max_delay: Duration::from_millis(30_000),
+            idle_timeout: Duration::from_secs(300),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Policy which never retries a failed request
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Calculate the delay before the given attempt (the initial attempt being 1).
+    /// Exponential backoff is used where the second half of the delay is randomized,
+    /// so parallel clients do not retry in lockstep.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(2).min(31);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+
+        let half = delay / 2;
+        half + half.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+    }
+
+    pub fn should_retry(&self, attempt: u32, error: &anyhow::Error) -> bool {
+        attempt < self.max_attempts && is_transient(error)
+    }
+}
+
+/// Detects chunk uploads which stopped making progress.
+/// Slow uploads are not affected as long as bytes keep being sent.
+#[derive(Debug)]
+pub struct StallDetector {
+    timeout: Duration,
+    bytes: u64,
+    last_progress: Instant,
+}
+
+impl StallDetector {
+    pub fn new(timeout: Duration, bytes: u64, now: Instant) -> Self {
+        Self {
+            timeout,
+            bytes,
+            last_progress: now,
+        }
+    }
+
+    /// Record the bytes uploaded so far.
+    /// Returns true if they did not advance within the timeout.
+    pub fn is_stalled(&mut self, bytes: u64, now: Instant) -> bool {
+        if bytes != self.bytes {
+            self.bytes = bytes;
+            self.last_progress = now;
+            return false;
+        }
+
+        now.duration_since(self.last_progress) >= self.timeout
+    }
+}
+
+/// Check if an error is only temporary and the request may succeed when sending it again.
+/// Server errors, timeouts and connection failures are considered transient
+/// where as client errors (e.g. the share size has been exceeded) are permanent.
+pub fn is_transient(error: &anyhow::Error) -> bool {
+    for cause in error.chain() {
+        if let Some(error) = cause.downcast_ref::<reqwest::Error>() {
+            if let Some(status) = error.status() {
+                return status.is_server_error()
+                    || status == StatusCode::REQUEST_TIMEOUT
+                    || status == StatusCode::TOO_MANY_REQUESTS;
+            }
+
+            if error.is_timeout() || error.is_connect() || error.is_request() || error.is_body() {
+                return true;
+            }
+        }
+
+        if let Some(error) = cause.downcast_ref::<io::Error>() {
+            if matches!(
+                error.kind(),
+                io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::TimedOut
+                    | io::ErrorKind::UnexpectedEof
+            ) {
+                return true;
+            }
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io,
+        time::{Duration, Instant},
+    };
+
+    use super::{is_transient, RetryPolicy, StallDetector};
+
+    #[test]
+    fn test_delay() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1_000),
+            ..Default::default()
+        };
+
+        for _ in 0..100 {
+            let delay = policy.delay(2);
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(100));
+
+            let delay = policy.delay(4);
+            assert!(delay >= Duration::from_millis(200) && delay <= Duration::from_millis(400));
+
+            let delay = policy.delay(9);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_millis(1_000));
+        }
+    }
+
+    #[test]
+    fn test_stall_detector() {
+        let start = Instant::now();
+        let seconds = |value| start + Duration::from_secs(value);
+        let mut detector = StallDetector::new(Duration::from_secs(30), 0, start);
+
+        /* a slow upload takes longer than the timeout in total, but keeps making progress */
+        for second in 1..=120 {
+            assert!(!detector.is_stalled(second * 100, seconds(second)));
+        }
+
+        assert!(!detector.is_stalled(12_000, seconds(149)));
+        assert!(detector.is_stalled(12_000, seconds(150)));
+        assert!(!detector.is_stalled(12_100, seconds(151)));
+    }
+
+    #[test]
+    fn test_transient() {
+        let error = anyhow::Error::new(io::Error::from(io::ErrorKind::ConnectionReset));
+        assert!(is_transient(&error));
+
+        let error = anyhow::Error::new(io::Error::from(io::ErrorKind::NotFound));
+        assert!(!is_transient(&error));
+
+        assert!(!is_transient(&anyhow::anyhow!("share size exceeded")));
+    }
+}
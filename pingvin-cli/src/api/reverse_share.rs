@@ -0,0 +1,214 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use super::{append_cookie, share::parse_size, ExpireDuration, PingvinApi};
+
+/// Options of a reverse share, which allows others to upload shares into the account
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReverseShareOptions {
+    /// Maximum size in bytes of each share uploaded using the reverse share
+    #[serde(serialize_with = "serialize_size")]
+    pub max_share_size: u64,
+
+    /// Expiration of the shares uploaded using the reverse share
+    pub share_expiration: ExpireDuration,
+
+    /// How many shares can be uploaded using the reverse share
+    pub max_use_count: u32,
+
+    /// Notify the owner by email when a share has been uploaded
+    pub send_email_notification: bool,
+
+    pub simplified: bool,
+    pub public_access: bool,
+}
+
+/// The server expects the size as string, as it may exceed the javascript number range
+fn serialize_size<S: serde::Serializer>(size: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&size.to_string())
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CreatedReverseShare {
+    #[allow(unused)]
+    pub token: String,
+
+    /// Link to the upload page of the reverse share
+    pub link: String,
+}
+
+/// Reverse share of the signed in user
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MyReverseShare {
+    pub id: String,
+    pub token: String,
+
+    #[serde(deserialize_with = "parse_size")]
+    pub max_share_size: u64,
+
+    pub share_expiration: DateTime<Utc>,
+
+    #[serde(default)]
+    pub remaining_uses: u32,
+
+    /// Shares which have been uploaded using the reverse share
+    #[serde(default)]
+    pub shares: Vec<ReverseShareUpload>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReverseShareUpload {
+    pub id: String,
+    /* other members have been omitted */
+}
+
+/// Reverse share as it is presented to uploaders
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReverseShare {
+    #[serde(deserialize_with = "parse_size")]
+    pub max_share_size: u64,
+    /* other members have been omitted */
+}
+
+impl PingvinApi {
+    /// Upload shares into the account which created the reverse share
+    /// instead of signing in
+    pub fn set_reverse_share_token(&mut self, token: &str) -> anyhow::Result<()> {
+        append_cookie(
+            &mut self.authentication_headers,
+            "reverse_share_token",
+            token,
+        )
+    }
+
+    /// Look up the reverse share of the given token.
+    /// Fails if the token is invalid, expired or has been used up.
+    pub async fn reverse_share(&self, token: &str) -> anyhow::Result<ReverseShare> {
+        let response = self
+            .http_client
+            .get(self.base_url.join(&format!("reverseShares/{}", token))?)
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            anyhow::bail!("The reverse share is invalid or has expired");
+        }
+
+        Ok(response.error_for_status()?.json().await?)
+    }
+
+    pub async fn create_reverse_share(
+        &self,
+        options: &ReverseShareOptions,
+    ) -> anyhow::Result<CreatedReverseShare> {
+        let response = self
+            .http_client
+            .post(self.base_url.join("reverseShares")?)
+            .json(options)
+            .headers(self.authentication_headers.clone())
+            .send()
+            .await?
+            .error_for_status()
+            .context("create reverse share")?;
+        Ok(response.json().await?)
+    }
+
+    /// Reverse shares created by the signed in user
+    pub async fn list_reverse_shares(&self) -> anyhow::Result<Vec<MyReverseShare>> {
+        let response = self
+            .http_client
+            .get(self.base_url.join("reverseShares")?)
+            .headers(self.authentication_headers.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.json().await?)
+    }
+
+    pub async fn delete_reverse_share(&self, reverse_share_id: &str) -> anyhow::Result<()> {
+        self.http_client
+            .delete(
+                self.base_url
+                    .join(&format!("reverseShares/{}", reverse_share_id))?,
+            )
+            .headers(self.authentication_headers.clone())
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::ReverseShareOptions;
+    use crate::api::{mock::MockServer, ExpireDuration, PingvinApi};
+
+    #[tokio::test]
+    async fn test_reverse_share() {
+        let server = MockServer::start(16).await;
+        server
+            .state()
+            .users
+            .insert("alice".to_string(), "secret".to_string());
+
+        let mut owner = PingvinApi::new(server.base_url()).unwrap();
+        owner.login("alice", "secret").await.unwrap();
+        let created = owner
+            .create_reverse_share(&ReverseShareOptions {
+                max_share_size: 1000,
+                share_expiration: ExpireDuration::Days(1),
+                max_use_count: 1,
+                send_email_notification: false,
+                simplified: false,
+                public_access: true,
+            })
+            .await
+            .unwrap();
+        assert_eq!(
+            created.link,
+            format!("http://pingvin.local/upload/{}", created.token)
+        );
+
+        let directory = tempfile::tempdir().unwrap();
+        let file = directory.path().join("file.bin");
+        fs::write(&file, [7u8; 20]).unwrap();
+
+        let mut uploader = PingvinApi::new(server.base_url()).unwrap();
+        assert!(uploader.reverse_share("invalid").await.is_err());
+        let reverse_share = uploader.reverse_share(&created.token).await.unwrap();
+        assert_eq!(reverse_share.max_share_size, 1000);
+
+        uploader.set_reverse_share_token(&created.token).unwrap();
+        let mut builder = uploader.create_share();
+        builder.set_id("reverse".to_string()).add_file(file);
+        builder.upload().await.unwrap();
+        assert_eq!(
+            server.state().shares["reverse"]
+                .reverse_share_token
+                .as_deref(),
+            Some(created.token.as_str())
+        );
+
+        /* the reverse share has been used up */
+        assert!(uploader.reverse_share(&created.token).await.is_err());
+
+        let reverse_shares = owner.list_reverse_shares().await.unwrap();
+        assert_eq!(reverse_shares.len(), 1);
+        assert_eq!(reverse_shares[0].remaining_uses, 0);
+        assert_eq!(reverse_shares[0].shares[0].id, "reverse");
+
+        owner
+            .delete_reverse_share(&reverse_shares[0].id)
+            .await
+            .unwrap();
+        assert!(owner.list_reverse_shares().await.unwrap().is_empty());
+    }
+}
@@ -1,6 +1,9 @@
 use std::error::Error;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
+use std::time::Duration;
 
+use chrono::{DateTime, Months, TimeDelta, Utc};
 use serde::de;
 use serde::Deserialize;
 use serde::Serialize;
@@ -59,6 +62,87 @@ impl ExpireDuration {
     }
 }
 
+impl ExpireDuration {
+    /// Calendar months and remaining seconds of the duration or None if it never ends
+    fn offset(self) -> Option<(u64, u64)> {
+        const HOUR: u64 = 60 * 60;
+        const DAY: u64 = 24 * HOUR;
+
+        Some(match self {
+            Self::Never => return None,
+            Self::Seconds(v) => (0, v),
+            Self::Minutes(v) => (0, v.checked_mul(60)?),
+            Self::Hour(v) => (0, v.checked_mul(HOUR)?),
+            Self::Days(v) => (0, v.checked_mul(DAY)?),
+            Self::Week(v) => (0, v.checked_mul(7 * DAY)?),
+            Self::Month(v) => (v, 0),
+            Self::Year(v) => (v.checked_mul(12)?, 0),
+        })
+    }
+
+    /// Time the duration ends at when starting at `start` or None if it never ends.
+    /// Months and years are added to the calendar date like the server does, hence one
+    /// month after January 31st is the last day of February.
+    pub fn after(self, start: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let (months, seconds) = self.offset()?;
+        start
+            .checked_add_months(Months::new(months.try_into().ok()?))?
+            .checked_add_signed(TimeDelta::seconds(seconds.try_into().ok()?))
+    }
+
+    /// Time the duration started at when ending at `end` or None if it never started.
+    /// Months and years are subtracted from the calendar date, see [`Self::after`].
+    pub fn before(self, end: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let (months, seconds) = self.offset()?;
+        end.checked_sub_months(Months::new(months.try_into().ok()?))?
+            .checked_sub_signed(TimeDelta::seconds(seconds.try_into().ok()?))
+    }
+
+    /// Check the expiration against the maximum expiration allowed by the server.
+    /// Expirations exceeding the maximum will be clamped to the maximum if requested.
+    pub fn limit(self, max_expiration: Option<Duration>, clamp: bool) -> anyhow::Result<Self> {
+        self.limit_at(max_expiration, clamp, Utc::now())
+    }
+
+    fn limit_at(
+        self,
+        max_expiration: Option<Duration>,
+        clamp: bool,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<Self> {
+        let Some(max_expiration) = max_expiration else {
+            return Ok(self);
+        };
+
+        /* the server checks the expiration date, hence months depend on the current date */
+        let max_expires_at = TimeDelta::from_std(max_expiration)
+            .ok()
+            .and_then(|max_expiration| now.checked_add_signed(max_expiration));
+        if self
+            .after(now)
+            .is_some_and(|expires_at| max_expires_at.is_none_or(|max| expires_at <= max))
+        {
+            return Ok(self);
+        }
+
+        let max_hours = max_expiration.as_secs() / (60 * 60);
+        if !clamp {
+            anyhow::bail!(
+                "The expiration {} exceeds the maximum expiration of {} hours allowed by the server.\nUse a shorter expiration or --clamp-expiration.",
+                self.to_string(),
+                max_hours
+            );
+        }
+
+        log::info!(
+            "Clamping the expiration {} to the maximum of {} hours",
+            self.to_string(),
+            max_hours
+        );
+        Ok(Self::Hour(max_hours))
+    }
+}
+
 impl FromStr for ExpireDuration {
     type Err = Box<dyn Error + Send + Sync + 'static>;
 
@@ -93,3 +177,201 @@ pub struct ShareSecurityOptions {
     #[serde(default)]
     password: Option<String>,
 }
+
+impl ShareSecurityOptions {
+    /// Length in characters the server accepts for share passwords
+    pub const PASSWORD_LENGTH: RangeInclusive<usize> = 3..=30;
+
+    pub fn new(password: Option<String>, max_views: Option<usize>) -> anyhow::Result<Self> {
+        if let Some(password) = &password {
+            if !Self::PASSWORD_LENGTH.contains(&password.chars().count()) {
+                anyhow::bail!(
+                    "the share password must be between {} and {} characters long",
+                    Self::PASSWORD_LENGTH.start(),
+                    Self::PASSWORD_LENGTH.end()
+                );
+            }
+        }
+
+        if max_views == Some(0) {
+            anyhow::bail!("the share must allow at least one view");
+        }
+
+        Ok(Self {
+            max_views,
+            password,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.max_views.is_none() && self.password.is_none()
+    }
+}
+
+/// Share of the signed in user as listed by the server
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MyShare {
+    pub id: String,
+
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// Expiration date of the share, shares which never expire use the unix epoch
+    pub expiration: DateTime<Utc>,
+
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+
+    #[serde(default)]
+    pub views: u64,
+
+    #[serde(default, deserialize_with = "parse_size")]
+    pub size: u64,
+
+    #[serde(default)]
+    pub files: Vec<ShareFile>,
+}
+
+impl MyShare {
+    /// Expiration date of the share or None if it never expires
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        (self.expiration.timestamp() != 0).then_some(self.expiration)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at()
+            .is_some_and(|expiration| expiration <= now)
+    }
+
+    /// Size of all files within the share.
+    /// Older servers do not report the share size, hence it falls back to the file sizes.
+    pub fn total_size(&self) -> u64 {
+        self.size
+            .max(self.files.iter().map(|file| file.size).sum::<u64>())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ShareFile {
+    pub id: String,
+    pub name: String,
+
+    #[serde(default, deserialize_with = "parse_size")]
+    pub size: u64,
+}
+
+/// The server reports file sizes as strings, as they may exceed the javascript number range
+pub(super) fn parse_size<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u64, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Number(n) => n
+            .as_u64()
+            .ok_or_else(|| de::Error::custom("size must not be negative")),
+        Value::String(s) => s.parse::<u64>().map_err(de::Error::custom),
+        _ => Err(de::Error::custom("Wrong type, expected number")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use chrono::{DateTime, Utc};
+
+    use super::{ExpireDuration, MyShare, ShareSecurityOptions};
+
+    #[test]
+    fn test_expiration_limit() {
+        let max = Some(Duration::from_secs(48 * 60 * 60));
+
+        let expiration = ExpireDuration::Days(2).limit(max, false).unwrap();
+        assert!(matches!(expiration, ExpireDuration::Days(2)));
+        let expiration = ExpireDuration::Never.limit(None, false).unwrap();
+        assert!(matches!(expiration, ExpireDuration::Never));
+
+        assert!(ExpireDuration::Week(1).limit(max, false).is_err());
+        assert!(ExpireDuration::Never.limit(max, false).is_err());
+
+        let expiration = ExpireDuration::Never.limit(max, true).unwrap();
+        assert!(matches!(expiration, ExpireDuration::Hour(48)));
+
+        /* months have the length of the calendar month */
+        let max = Some(Duration::from_secs(30 * 24 * 60 * 60));
+        let february = "2024-02-01T00:00:00Z".parse().unwrap();
+        assert!(ExpireDuration::Month(1)
+            .limit_at(max, false, february)
+            .is_ok());
+        let january = "2024-01-01T00:00:00Z".parse().unwrap();
+        assert!(ExpireDuration::Month(1)
+            .limit_at(max, false, january)
+            .is_err());
+    }
+
+    #[test]
+    fn test_calendar_duration() {
+        let start: DateTime<Utc> = "2024-01-31T12:00:00Z".parse().unwrap();
+        assert_eq!(
+            ExpireDuration::Month(1).after(start),
+            Some("2024-02-29T12:00:00Z".parse().unwrap())
+        );
+        assert_eq!(
+            ExpireDuration::Year(1).after(start),
+            Some("2025-01-31T12:00:00Z".parse().unwrap())
+        );
+        assert_eq!(
+            ExpireDuration::Days(2).after(start),
+            Some("2024-02-02T12:00:00Z".parse().unwrap())
+        );
+        assert_eq!(
+            ExpireDuration::Month(1).before("2024-03-31T00:00:00Z".parse().unwrap()),
+            Some("2024-02-29T00:00:00Z".parse().unwrap())
+        );
+        assert_eq!(ExpireDuration::Never.after(start), None);
+        assert_eq!(ExpireDuration::Year(u64::MAX).after(start), None);
+    }
+
+    #[test]
+    fn test_security_options() {
+        let options = ShareSecurityOptions::new(Some("secret".to_string()), Some(3)).unwrap();
+        assert_eq!(
+            serde_json::to_value(&options).unwrap(),
+            serde_json::json!({ "password": "secret", "maxViews": 3 })
+        );
+        assert!(ShareSecurityOptions::new(None, None).unwrap().is_empty());
+
+        assert!(ShareSecurityOptions::new(Some("ab".to_string()), None).is_err());
+        assert!(ShareSecurityOptions::new(Some("a".repeat(31)), None).is_err());
+        assert!(ShareSecurityOptions::new(None, Some(0)).is_err());
+    }
+
+    #[test]
+    fn test_parse_my_share() {
+        let share: MyShare = serde_json::from_value(serde_json::json!({
+            "id": "abc",
+            "name": null,
+            "expiration": "1970-01-01T00:00:00.000Z",
+            "views": 3,
+            "files": [
+                { "id": "f1", "name": "a.txt", "size": "1000" },
+                { "id": "f2", "name": "b.txt", "size": "24" }
+            ],
+        }))
+        .unwrap();
+        assert!(share.expires_at().is_none());
+        assert!(!share.is_expired(chrono::Utc::now()));
+        assert_eq!(share.total_size(), 1024);
+
+        let share: MyShare = serde_json::from_value(serde_json::json!({
+            "id": "def",
+            "name": "Photos",
+            "expiration": "2024-01-01T12:00:00.000Z",
+            "views": 0,
+            "size": 2048,
+        }))
+        .unwrap();
+        assert!(share.is_expired(chrono::Utc::now()));
+        assert_eq!(share.total_size(), 2048);
+    }
+}
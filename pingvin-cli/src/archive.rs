@@ -0,0 +1,302 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use bytes::Bytes;
+use clap::ValueEnum;
+use flate2::{write::GzEncoder, Compression};
+use futures::Stream;
+use tokio::{io::AsyncRead, sync::mpsc, task::JoinHandle};
+use tokio_util::io::StreamReader;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::api::UploadFile;
+
+#[derive(ValueEnum, Clone, Debug, PartialEq, Copy)]
+pub enum ArchiveFormat {
+    Zip,
+
+    #[value(name = "tar.gz")]
+    TarGz,
+
+    #[value(name = "tar.zst")]
+    TarZst,
+}
+
+impl ArchiveFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Zip => "zip",
+            Self::TarGz => "tar.gz",
+            Self::TarZst => "tar.zst",
+        }
+    }
+}
+
+/// Amount of archive blocks which may be buffered before the archive writer has to wait for the upload
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Forwards everything written into it to the async reader side of the archive stream
+struct ChannelWriter {
+    sender: mpsc::Sender<io::Result<Bytes>>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sender
+            .blocking_send(Ok(Bytes::copy_from_slice(buf)))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "archive reader closed"))?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Derive the name of the archive from the selected paths.
+/// A single selected directory names the archive, otherwise the common parent folder is used.
+pub fn archive_name(paths: &[PathBuf], format: ArchiveFormat) -> String {
+    let paths = paths
+        .iter()
+        .filter_map(|path| path.canonicalize().ok())
+        .collect::<Vec<_>>();
+
+    let base_path = match paths.as_slice() {
+        [path] if path.is_dir() => Some(path.clone()),
+        [first, remaining @ ..] => {
+            let mut common = first.parent().map(Path::to_path_buf);
+            for path in remaining {
+                while let Some(candidate) = &common {
+                    if path.starts_with(candidate) {
+                        break;
+                    }
+                    common = candidate.parent().map(Path::to_path_buf);
+                }
+            }
+            common
+        }
+        [] => None,
+    };
+
+    let base_name = base_path
+        .as_ref()
+        .and_then(|path| path.file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "archive".to_string());
+
+    format!("{}.{}", base_name, format.extension())
+}
+
+fn write_zip(files: &[UploadFile], writer: impl Write) -> anyhow::Result<()> {
+    let mut zip = ZipWriter::new_stream(writer);
+    for file in files {
+        let length = file.path.metadata()?.len();
+        let options = SimpleFileOptions::default()
+            .compression_method(CompressionMethod::Deflated)
+            .large_file(length >= u32::MAX as u64);
+
+        zip.start_file(file.name.as_str(), options)?;
+        let mut reader =
+            File::open(&file.path).with_context(|| format!("open {}", file.path.display()))?;
+        io::copy(&mut reader, &mut zip)?;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+fn write_tar<W: Write>(files: &[UploadFile], writer: W) -> anyhow::Result<W> {
+    let mut tar = tar::Builder::new(writer);
+    for file in files {
+        tar.append_path_with_name(&file.path, &file.name)
+            .with_context(|| format!("append {}", file.path.display()))?;
+    }
+
+    Ok(tar.into_inner()?)
+}
+
+fn write_archive(
+    format: ArchiveFormat,
+    files: &[UploadFile],
+    writer: ChannelWriter,
+) -> anyhow::Result<()> {
+    match format {
+        ArchiveFormat::Zip => write_zip(files, writer)?,
+        ArchiveFormat::TarGz => {
+            write_tar(files, GzEncoder::new(writer, Compression::default()))?.finish()?;
+        }
+        ArchiveFormat::TarZst => {
+            write_tar(files, zstd::Encoder::new(writer, 0)?)?.finish()?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Pack the given files into an archive on the fly.
+/// The archive is written by a blocking worker and can be read from the returned reader,
+/// without ever storing the whole archive on disk or in memory.
+pub fn create_archive_stream(
+    format: ArchiveFormat,
+    files: Vec<UploadFile>,
+) -> (impl AsyncRead + Unpin, JoinHandle<anyhow::Result<()>>) {
+    let (sender, receiver) = mpsc::channel(CHANNEL_CAPACITY);
+
+    let worker = tokio::task::spawn_blocking(move || {
+        let result = write_archive(
+            format,
+            &files,
+            ChannelWriter {
+                sender: sender.clone(),
+            },
+        );
+
+        if let Err(err) = &result {
+            /* abort the upload, otherwise a truncated archive would be uploaded */
+            let _ = sender.blocking_send(Err(io::Error::other(format!(
+                "failed to create archive: {:#}",
+                err
+            ))));
+        }
+
+        result
+    });
+
+    (StreamReader::new(receiver_stream(receiver)), worker)
+}
+
+fn receiver_stream(
+    receiver: mpsc::Receiver<io::Result<Bytes>>,
+) -> impl Stream<Item = io::Result<Bytes>> + Unpin {
+    Box::pin(futures::stream::unfold(
+        receiver,
+        |mut receiver| async move { receiver.recv().await.map(|item| (item, receiver)) },
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, io::Read};
+
+    use tokio::io::AsyncReadExt;
+
+    use super::{archive_name, create_archive_stream, ArchiveFormat};
+    use crate::api::UploadFile;
+
+    fn test_files(directory: &std::path::Path) -> Vec<UploadFile> {
+        fs::create_dir_all(directory.join("photos/2024")).unwrap();
+        fs::write(directory.join("photos/2024/a.txt"), "first file").unwrap();
+        fs::write(directory.join("photos/b.txt"), vec![7u8; 100_000]).unwrap();
+
+        vec![
+            UploadFile {
+                path: directory.join("photos/2024/a.txt"),
+                name: "photos/2024/a.txt".to_string(),
+            },
+            UploadFile {
+                path: directory.join("photos/b.txt"),
+                name: "photos/b.txt".to_string(),
+            },
+        ]
+    }
+
+    async fn read_archive(format: ArchiveFormat, files: Vec<UploadFile>) -> Vec<u8> {
+        let (mut reader, worker) = create_archive_stream(format, files);
+        let mut buffer = vec![];
+        reader.read_to_end(&mut buffer).await.unwrap();
+        worker.await.unwrap().unwrap();
+        buffer
+    }
+
+    #[tokio::test]
+    async fn test_tar_gz() {
+        let directory = tempfile::tempdir().unwrap();
+        let files = test_files(directory.path());
+
+        let buffer = read_archive(ArchiveFormat::TarGz, files).await;
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(buffer.as_slice()));
+        let mut entries = vec![];
+        for entry in archive.entries().unwrap() {
+            let mut entry = entry.unwrap();
+            let mut content = vec![];
+            entry.read_to_end(&mut content).unwrap();
+            entries.push((entry.path().unwrap().display().to_string(), content.len()));
+        }
+
+        assert_eq!(
+            entries,
+            vec![
+                ("photos/2024/a.txt".to_string(), 10),
+                ("photos/b.txt".to_string(), 100_000)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_zip() {
+        let directory = tempfile::tempdir().unwrap();
+        let files = test_files(directory.path());
+
+        let buffer = read_archive(ArchiveFormat::Zip, files).await;
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(buffer)).unwrap();
+        assert_eq!(archive.len(), 2);
+
+        let mut content = String::new();
+        archive
+            .by_name("photos/2024/a.txt")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "first file");
+        assert_eq!(archive.by_name("photos/b.txt").unwrap().size(), 100_000);
+    }
+
+    #[tokio::test]
+    async fn test_tar_zst() {
+        let directory = tempfile::tempdir().unwrap();
+        let files = test_files(directory.path());
+
+        let buffer = read_archive(ArchiveFormat::TarZst, files).await;
+        let decoder = zstd::Decoder::new(buffer.as_slice()).unwrap();
+        let mut archive = tar::Archive::new(decoder);
+        assert_eq!(archive.entries().unwrap().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_missing_file() {
+        let directory = tempfile::tempdir().unwrap();
+        let files = vec![UploadFile {
+            path: directory.path().join("missing.txt"),
+            name: "missing.txt".to_string(),
+        }];
+
+        let (mut reader, worker) = create_archive_stream(ArchiveFormat::TarGz, files);
+        let mut buffer = vec![];
+        assert!(reader.read_to_end(&mut buffer).await.is_err());
+        assert!(worker.await.unwrap().is_err());
+    }
+
+    #[test]
+    fn test_archive_name() {
+        let directory = tempfile::tempdir().unwrap();
+        let files = test_files(directory.path());
+        let photos = directory.path().join("photos");
+
+        assert_eq!(
+            archive_name(std::slice::from_ref(&photos), ArchiveFormat::Zip),
+            "photos.zip"
+        );
+        assert_eq!(
+            archive_name(
+                &[files[0].path.clone(), files[1].path.clone()],
+                ArchiveFormat::TarGz
+            ),
+            "photos.tar.gz"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+#[cfg(not(target_os = "windows"))]
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+#[cfg(not(target_os = "windows"))]
+use anyhow::Context;
+use clap::ValueEnum;
+
+use crate::api::{self, UploadedShare};
+
+/// Clipboard of the desktop session the text can be copied to
+pub trait Clipboard {
+    fn set_text(&self, text: &str) -> anyhow::Result<()>;
+}
+
+/// Copies the text using a command line tool which reads it from stdin,
+/// like wl-copy on Wayland or xclip on X11
+#[cfg(not(target_os = "windows"))]
+pub struct CommandClipboard {
+    program: &'static str,
+    args: &'static [&'static str],
+}
+
+#[cfg(not(target_os = "windows"))]
+impl CommandClipboard {
+    #[cfg(not(target_os = "macos"))]
+    pub const WAYLAND: Self = Self {
+        program: "wl-copy",
+        args: &[],
+    };
+
+    #[cfg(not(target_os = "macos"))]
+    pub const X11: Self = Self {
+        program: "xclip",
+        args: &["-selection", "clipboard"],
+    };
+
+    #[cfg(target_os = "macos")]
+    pub const MACOS: Self = Self {
+        program: "pbcopy",
+        args: &[],
+    };
+}
+
+#[cfg(not(target_os = "windows"))]
+impl Clipboard for CommandClipboard {
+    fn set_text(&self, text: &str) -> anyhow::Result<()> {
+        let mut child = Command::new(self.program)
+            .args(self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| format!("run {}", self.program))?;
+        {
+            let mut stdin = child.stdin.take().context("missing stdin")?;
+            stdin.write_all(text.as_bytes())?;
+        }
+
+        /* wl-copy and xclip keep serving the clipboard within a background process */
+        let status = child.wait()?;
+        if !status.success() {
+            anyhow::bail!("{} failed with {}", self.program, status);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub struct WindowsClipboard;
+
+#[cfg(target_os = "windows")]
+impl Clipboard for WindowsClipboard {
+    fn set_text(&self, text: &str) -> anyhow::Result<()> {
+        clipboard_win::set_clipboard_string(text)
+            .map_err(|err| anyhow::anyhow!("set clipboard: {}", err))
+    }
+}
+
+/// Records the copied text instead of touching the clipboard of the session
+#[cfg(test)]
+#[derive(Default)]
+pub struct FakeClipboard {
+    pub text: std::cell::RefCell<Option<String>>,
+}
+
+#[cfg(test)]
+impl Clipboard for FakeClipboard {
+    fn set_text(&self, text: &str) -> anyhow::Result<()> {
+        *self.text.borrow_mut() = Some(text.to_string());
+        Ok(())
+    }
+}
+
+/// The clipboard of the current platform or desktop session
+pub fn system() -> anyhow::Result<Box<dyn Clipboard>> {
+    #[cfg(target_os = "windows")]
+    return Ok(Box::new(WindowsClipboard));
+
+    #[cfg(target_os = "macos")]
+    return Ok(Box::new(CommandClipboard::MACOS));
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            return Ok(Box::new(CommandClipboard::WAYLAND));
+        }
+        if std::env::var_os("DISPLAY").is_some() {
+            return Ok(Box::new(CommandClipboard::X11));
+        }
+        anyhow::bail!("there is no clipboard, as neither a Wayland nor an X11 session is running");
+    }
+}
+
+/// Link of the uploaded share which will be copied to the clipboard
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CopyLink {
+    /// The page of the share within the web interface
+    Share,
+
+    /// The download link of the file, if the share contains a single file.
+    /// Falls back to the share page otherwise.
+    File,
+
+    /// The download link of all files as zip archive
+    Zip,
+}
+
+impl CopyLink {
+    pub fn url(self, app_url: &str, share: &UploadedShare) -> String {
+        match (self, share.files.as_slice()) {
+            (CopyLink::Share, _) => api::share_url(app_url, &share.id),
+            (CopyLink::File, [file]) => api::file_url(app_url, &share.id, &file.id),
+            (CopyLink::File, files) => {
+                log::warn!(
+                    "The share contains {} files, copying the link of the share page instead",
+                    files.len()
+                );
+                api::share_url(app_url, &share.id)
+            }
+            (CopyLink::Zip, _) => api::zip_url(app_url, &share.id),
+        }
+    }
+}
+
+/// Copy the selected link of the uploaded share to the clipboard
+pub fn copy_link(
+    clipboard: &dyn Clipboard,
+    link: CopyLink,
+    app_url: &str,
+    share: &UploadedShare,
+) -> anyhow::Result<()> {
+    let url = link.url(app_url, share);
+    clipboard.set_text(&url)?;
+    log::info!("Copied {} to the clipboard", url);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{copy_link, CopyLink, FakeClipboard};
+    use crate::api::{UploadedFile, UploadedShare};
+
+    #[test]
+    fn test_copy_link() {
+        let clipboard = FakeClipboard::default();
+        let copied = |link, files: &[&str]| {
+            let share = UploadedShare {
+                id: "holiday".to_string(),
+                files: files
+                    .iter()
+                    .map(|id| UploadedFile { id: id.to_string() })
+                    .collect(),
+            };
+            copy_link(&clipboard, link, "https://share.example.com", &share).unwrap();
+            clipboard.text.borrow_mut().take().unwrap()
+        };
+
+        assert_eq!(
+            copied(CopyLink::Share, &["a"]),
+            "https://share.example.com/s/holiday"
+        );
+        assert_eq!(
+            copied(CopyLink::File, &["a"]),
+            "https://share.example.com/api/shares/holiday/files/a"
+        );
+        assert_eq!(
+            copied(CopyLink::File, &["a", "b"]),
+            "https://share.example.com/s/holiday"
+        );
+        assert_eq!(
+            copied(CopyLink::Zip, &["a", "b"]),
+            "https://share.example.com/api/shares/holiday/files/zip"
+        );
+    }
+}
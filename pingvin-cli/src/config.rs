@@ -0,0 +1,187 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::{api::ExpireDuration, credentials::PasswordSource, output::OutputType};
+
+/// Client configuration containing named server profiles
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ClientConfig {
+    /// Profile which will be used if no profile has been selected with --profile
+    pub default_profile: Option<String>,
+
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Profile {
+    pub server_url: Option<String>,
+
+    pub username: Option<String>,
+
+    /// Name of the environment variable containing the password
+    pub password_env: Option<String>,
+
+    /// File containing the password
+    pub password_file: Option<PathBuf>,
+
+    /// Take the password from the OS keyring
+    #[serde(default)]
+    pub password_keyring: bool,
+
+    /// Ask for the password on the terminal
+    #[serde(default)]
+    pub password_prompt: bool,
+
+    /// Base32 encoded secret to generate TOTP codes for users with two-factor authentication
+    pub totp_secret: Option<String>,
+
+    /// Default expiration of created shares
+    pub expiration: Option<ExpireDuration>,
+
+    /// Default output type
+    pub output: Option<OutputType>,
+}
+
+impl Profile {
+    pub fn server_url(&self) -> anyhow::Result<Option<Url>> {
+        self.server_url
+            .as_deref()
+            .map(Url::parse)
+            .transpose()
+            .context("invalid profile server url")
+    }
+
+    /// Where the password of the profile can be found.
+    /// Passwords are never stored within the config file itself.
+    pub fn password_source(&self) -> Option<PasswordSource> {
+        if let Some(name) = &self.password_env {
+            Some(PasswordSource::Env(name.clone()))
+        } else if let Some(path) = &self.password_file {
+            Some(PasswordSource::File(path.clone()))
+        } else if self.password_keyring {
+            Some(PasswordSource::Keyring)
+        } else if self.password_prompt {
+            Some(PasswordSource::Prompt)
+        } else {
+            None
+        }
+    }
+}
+
+/// Path of the client configuration file
+pub fn config_path() -> anyhow::Result<PathBuf> {
+    Ok(dirs::config_dir()
+        .context("missing config directory")?
+        .join("pingvin")
+        .join("config.toml"))
+}
+
+impl ClientConfig {
+    /// Load the configuration from the given path.
+    /// A missing configuration file results in an empty configuration.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content =
+            fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("parse {}", path.display()))
+    }
+
+    /// Get the profile with the given name or the default profile if no name has been given
+    pub fn profile(&self, name: Option<&str>) -> anyhow::Result<Profile> {
+        let Some(name) = name.or(self.default_profile.as_deref()) else {
+            return Ok(Profile::default());
+        };
+
+        self.profiles
+            .get(name)
+            .cloned()
+            .with_context(|| format!("unknown profile {}", name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::ClientConfig;
+    use crate::{api::ExpireDuration, credentials::PasswordSource, output::OutputType};
+
+    const CONFIG: &str = r#"
+default-profile = "home"
+
+[profiles.home]
+server-url = "https://share.example.com"
+username = "alice"
+password-file = "password.txt"
+
+[profiles.work]
+server-url = "https://share.example.org"
+totp-secret = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ"
+expiration = "7-days"
+output = "windows-notification"
+"#;
+
+    #[test]
+    fn test_profiles() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("config.toml");
+        fs::write(&path, CONFIG).unwrap();
+
+        let config = ClientConfig::load(&path).unwrap();
+        let profile = config.profile(None).unwrap();
+        assert_eq!(profile.username.as_deref(), Some("alice"));
+        assert_eq!(
+            profile.password_source(),
+            Some(PasswordSource::File("password.txt".into()))
+        );
+        assert_eq!(
+            profile.server_url().unwrap().unwrap().as_str(),
+            "https://share.example.com/"
+        );
+
+        let profile = config.profile(Some("work")).unwrap();
+        assert!(profile.username.is_none());
+        assert!(profile.totp_secret.is_some());
+        assert!(matches!(profile.expiration, Some(ExpireDuration::Days(7))));
+        assert_eq!(profile.output, Some(OutputType::WindowsNotification));
+
+        assert!(config.profile(Some("missing")).is_err());
+    }
+
+    #[test]
+    fn test_missing_config() {
+        let directory = tempfile::tempdir().unwrap();
+        let config = ClientConfig::load(&directory.path().join("config.toml")).unwrap();
+        assert!(config.profile(None).unwrap().server_url.is_none());
+        assert!(config.profile(Some("work")).is_err());
+    }
+
+    #[test]
+    fn test_password_source() {
+        let config: ClientConfig = toml::from_str(
+            r#"
+[profiles.keyring]
+username = "alice"
+password-keyring = true
+"#,
+        )
+        .unwrap();
+
+        let profile = config.profile(Some("keyring")).unwrap();
+        assert_eq!(profile.password_source(), Some(PasswordSource::Keyring));
+        assert_eq!(config.profile(None).unwrap().password_source(), None);
+    }
+}
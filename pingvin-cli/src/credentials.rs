@@ -0,0 +1,337 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::Context;
+use reqwest::Url;
+
+/// Service name the passwords will be stored with within the OS keyring
+const KEYRING_SERVICE: &str = "pingvin-cli";
+
+/// Password storage provided by the operating system
+pub trait Keyring {
+    fn get_password(&self, account: &str) -> anyhow::Result<Option<String>>;
+    fn set_password(&self, account: &str, password: &str) -> anyhow::Result<()>;
+
+    /// Remove the password of the account.
+    /// Returns false if there has been no password stored.
+    fn delete_password(&self, account: &str) -> anyhow::Result<bool>;
+}
+
+/// Keyring backed by the freedesktop Secret Service (e.g. GNOME Keyring or KWallet)
+#[cfg(target_os = "linux")]
+pub struct SecretServiceKeyring;
+
+#[cfg(target_os = "linux")]
+impl SecretServiceKeyring {
+    fn attributes(account: &str) -> std::collections::HashMap<&str, &str> {
+        [("service", KEYRING_SERVICE), ("account", account)].into()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Keyring for SecretServiceKeyring {
+    fn get_password(&self, account: &str) -> anyhow::Result<Option<String>> {
+        use secret_service::{blocking::SecretService, EncryptionType};
+
+        let service =
+            SecretService::connect(EncryptionType::Dh).context("connect secret service")?;
+        let result = service.search_items(Self::attributes(account))?;
+        let Some(item) = result.unlocked.first().or(result.locked.first()) else {
+            return Ok(None);
+        };
+
+        item.ensure_unlocked()?;
+        let secret = item.get_secret()?;
+        Ok(Some(
+            String::from_utf8(secret).context("password is not valid utf-8")?,
+        ))
+    }
+
+    fn set_password(&self, account: &str, password: &str) -> anyhow::Result<()> {
+        use secret_service::{blocking::SecretService, EncryptionType};
+
+        let service =
+            SecretService::connect(EncryptionType::Dh).context("connect secret service")?;
+        let collection = service.get_default_collection()?;
+        collection.ensure_unlocked()?;
+        collection.create_item(
+            &format!("Pingvin Share password for {}", account),
+            Self::attributes(account),
+            password.as_bytes(),
+            true,
+            "text/plain",
+        )?;
+        Ok(())
+    }
+
+    fn delete_password(&self, account: &str) -> anyhow::Result<bool> {
+        use secret_service::{blocking::SecretService, EncryptionType};
+
+        let service =
+            SecretService::connect(EncryptionType::Dh).context("connect secret service")?;
+        let result = service.search_items(Self::attributes(account))?;
+
+        let mut deleted = false;
+        for item in result.unlocked.iter().chain(result.locked.iter()) {
+            item.delete()?;
+            deleted = true;
+        }
+        Ok(deleted)
+    }
+}
+
+/// Keyring backed by the Windows Credential Manager
+#[cfg(target_os = "windows")]
+pub struct CredentialManagerKeyring;
+
+#[cfg(target_os = "windows")]
+impl CredentialManagerKeyring {
+    /// Null terminated name of the credential
+    fn target(account: &str) -> Vec<u16> {
+        format!("{}:{}", KEYRING_SERVICE, account)
+            .encode_utf16()
+            .chain(Some(0))
+            .collect()
+    }
+}
+
+#[cfg(target_os = "windows")]
+impl Keyring for CredentialManagerKeyring {
+    fn get_password(&self, account: &str) -> anyhow::Result<Option<String>> {
+        use windows::{
+            core::PCWSTR,
+            Win32::{
+                Foundation::ERROR_NOT_FOUND,
+                Security::Credentials::{CredFree, CredReadW, CREDENTIALW, CRED_TYPE_GENERIC},
+            },
+        };
+
+        let target = Self::target(account);
+        let mut credential: *mut CREDENTIALW = std::ptr::null_mut();
+        let result = unsafe {
+            CredReadW(
+                PCWSTR(target.as_ptr()),
+                CRED_TYPE_GENERIC,
+                0,
+                &mut credential,
+            )
+        };
+        match result {
+            Ok(()) => {}
+            Err(err) if err.code() == ERROR_NOT_FOUND.to_hresult() => return Ok(None),
+            Err(err) => return Err(err).context("read credential"),
+        }
+
+        let secret = unsafe {
+            let length = (*credential).CredentialBlobSize as usize;
+            let secret = match length {
+                0 => vec![],
+                length => std::slice::from_raw_parts((*credential).CredentialBlob, length).to_vec(),
+            };
+            CredFree(credential as *const _);
+            secret
+        };
+        Ok(Some(
+            String::from_utf8(secret).context("password is not valid utf-8")?,
+        ))
+    }
+
+    fn set_password(&self, account: &str, password: &str) -> anyhow::Result<()> {
+        use windows::{
+            core::PWSTR,
+            Win32::Security::Credentials::{
+                CredWriteW, CREDENTIALW, CRED_PERSIST_LOCAL_MACHINE, CRED_TYPE_GENERIC,
+            },
+        };
+
+        let mut target = Self::target(account);
+        let mut username = account.encode_utf16().chain(Some(0)).collect::<Vec<_>>();
+        let mut secret = password.as_bytes().to_vec();
+        let credential = CREDENTIALW {
+            Type: CRED_TYPE_GENERIC,
+            TargetName: PWSTR(target.as_mut_ptr()),
+            UserName: PWSTR(username.as_mut_ptr()),
+            CredentialBlobSize: secret.len() as u32,
+            CredentialBlob: secret.as_mut_ptr(),
+            Persist: CRED_PERSIST_LOCAL_MACHINE,
+            ..Default::default()
+        };
+        unsafe { CredWriteW(&credential, 0) }.context("write credential")?;
+        Ok(())
+    }
+
+    fn delete_password(&self, account: &str) -> anyhow::Result<bool> {
+        use windows::{
+            core::PCWSTR,
+            Win32::{
+                Foundation::ERROR_NOT_FOUND,
+                Security::Credentials::{CredDeleteW, CRED_TYPE_GENERIC},
+            },
+        };
+
+        let target = Self::target(account);
+        match unsafe { CredDeleteW(PCWSTR(target.as_ptr()), CRED_TYPE_GENERIC, 0) } {
+            Ok(()) => Ok(true),
+            Err(err) if err.code() == ERROR_NOT_FOUND.to_hresult() => Ok(false),
+            Err(err) => Err(err).context("delete credential"),
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+pub struct UnsupportedKeyring;
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+impl Keyring for UnsupportedKeyring {
+    fn get_password(&self, _account: &str) -> anyhow::Result<Option<String>> {
+        anyhow::bail!("there is no keyring available on this platform")
+    }
+
+    fn set_password(&self, _account: &str, _password: &str) -> anyhow::Result<()> {
+        anyhow::bail!("there is no keyring available on this platform")
+    }
+
+    fn delete_password(&self, _account: &str) -> anyhow::Result<bool> {
+        anyhow::bail!("there is no keyring available on this platform")
+    }
+}
+
+/// Keyring keeping the passwords in memory only
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryKeyring {
+    entries: std::sync::Mutex<std::collections::HashMap<String, String>>,
+}
+
+#[cfg(test)]
+impl Keyring for MemoryKeyring {
+    fn get_password(&self, account: &str) -> anyhow::Result<Option<String>> {
+        Ok(self.entries.lock().unwrap().get(account).cloned())
+    }
+
+    fn set_password(&self, account: &str, password: &str) -> anyhow::Result<()> {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(account.to_string(), password.to_string());
+        Ok(())
+    }
+
+    fn delete_password(&self, account: &str) -> anyhow::Result<bool> {
+        Ok(self.entries.lock().unwrap().remove(account).is_some())
+    }
+}
+
+/// The keyring of the current platform
+pub fn default_keyring() -> Box<dyn Keyring + Send> {
+    #[cfg(target_os = "linux")]
+    return Box::new(SecretServiceKeyring);
+
+    #[cfg(target_os = "windows")]
+    return Box::new(CredentialManagerKeyring);
+
+    #[cfg(not(any(target_os = "linux", target_os = "windows")))]
+    return Box::new(UnsupportedKeyring);
+}
+
+/// Name of the keyring entry for the given user on the given server
+pub fn keyring_account(server_url: &Url, username: &str) -> String {
+    let host = server_url.host_str().unwrap_or_default();
+    match server_url.port() {
+        Some(port) => format!("{}@{}:{}", username, host, port),
+        None => format!("{}@{}", username, host),
+    }
+}
+
+/// Where the password used to login will be taken from
+#[derive(Debug, Clone, PartialEq)]
+pub enum PasswordSource {
+    /// Name of an environment variable containing the password
+    Env(String),
+
+    /// File containing the password
+    File(PathBuf),
+
+    /// Password stored within the OS keyring
+    Keyring,
+
+    /// Ask for the password on the terminal
+    Prompt,
+}
+
+impl PasswordSource {
+    pub fn resolve(&self, account: &str, keyring: &dyn Keyring) -> anyhow::Result<String> {
+        match self {
+            Self::Env(name) => std::env::var(name)
+                .with_context(|| format!("read password from environment variable {}", name)),
+            Self::File(path) => {
+                let password = fs::read_to_string(path)
+                    .with_context(|| format!("read password from {}", path.display()))?;
+                Ok(password.trim_end_matches(['\r', '\n']).to_string())
+            }
+            Self::Keyring => keyring
+                .get_password(account)?
+                .with_context(|| format!("there is no password stored for {}", account)),
+            Self::Prompt => prompt_password(account),
+        }
+    }
+}
+
+pub fn prompt_password(account: &str) -> anyhow::Result<String> {
+    rpassword::prompt_password(format!("Password for {}: ", account)).context("read password")
+}
+
+pub fn prompt_refresh_token() -> anyhow::Result<String> {
+    rpassword::prompt_password("Refresh token: ").context("read refresh token")
+}
+
+pub fn prompt_totp() -> anyhow::Result<String> {
+    let code = rpassword::prompt_password("TOTP code: ").context("read totp code")?;
+    Ok(code.trim().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use reqwest::Url;
+
+    use super::{keyring_account, Keyring, MemoryKeyring, PasswordSource};
+
+    #[test]
+    fn test_password_sources() {
+        let keyring = MemoryKeyring::default();
+        let url = Url::parse("https://share.example.com:8443/api/").unwrap();
+        let account = keyring_account(&url, "alice");
+        assert_eq!(account, "alice@share.example.com:8443");
+
+        assert!(PasswordSource::Keyring.resolve(&account, &keyring).is_err());
+        keyring.set_password(&account, "keyring secret").unwrap();
+        assert_eq!(
+            PasswordSource::Keyring.resolve(&account, &keyring).unwrap(),
+            "keyring secret"
+        );
+        assert!(keyring.delete_password(&account).unwrap());
+        assert!(!keyring.delete_password(&account).unwrap());
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("password.txt");
+        fs::write(&path, "file secret\r\n").unwrap();
+        assert_eq!(
+            PasswordSource::File(path)
+                .resolve(&account, &keyring)
+                .unwrap(),
+            "file secret"
+        );
+
+        std::env::set_var("PINGVIN_TEST_PASSWORD", "env secret");
+        assert_eq!(
+            PasswordSource::Env("PINGVIN_TEST_PASSWORD".to_string())
+                .resolve(&account, &keyring)
+                .unwrap(),
+            "env secret"
+        );
+        assert!(PasswordSource::Env("PINGVIN_TEST_MISSING".to_string())
+            .resolve(&account, &keyring)
+            .is_err());
+    }
+}
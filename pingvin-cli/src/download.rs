@@ -0,0 +1,132 @@
+use std::path::PathBuf;
+
+use reqwest::Url;
+
+use crate::{
+    api::{self, OpenShareResult, PingvinApi},
+    credentials::PasswordSource,
+    files,
+    output::AppOutput,
+    resolve_password,
+    shares::{format_size, format_table},
+    SharePasswordArgs,
+};
+
+#[derive(Debug, clap::Args)]
+pub struct DownloadArgs {
+    /// URL of the share (e.g. https://share.example.com/s/abc) or its id
+    pub share: String,
+
+    /// Directory the files will be written to
+    #[arg(short, long, default_value = ".")]
+    pub target_directory: PathBuf,
+
+    /// Only download files matching the given pattern.
+    /// Can be specified multiple times.
+    #[arg(long = "file", value_name = "PATTERN")]
+    pub files: Vec<String>,
+
+    /// Download all files as a single zip archive created by the server
+    #[arg(long, conflicts_with = "files")]
+    pub zip: bool,
+
+    /// Only list the files of the share
+    #[arg(long, conflicts_with = "zip")]
+    pub list: bool,
+
+    /// Skip files which already exist with the same size as within the share.
+    /// The content is not compared, as the server provides no checksums.
+    #[arg(long, conflicts_with = "zip")]
+    pub skip_existing: bool,
+
+    #[command(flatten)]
+    pub share_password: SharePasswordArgs,
+}
+
+/// Split the link of a share into the API URL of the server and the share id.
+/// Returns None if the value is no share link.
+pub fn parse_share_url(value: &str) -> Option<(Url, String)> {
+    api::parse_frontend_url(&Url::parse(value).ok()?, &["s", "share"])
+}
+
+pub async fn download(
+    server_api: &PingvinApi,
+    share_id: &str,
+    args: &DownloadArgs,
+    output: &dyn AppOutput,
+) -> anyhow::Result<()> {
+    let password = args.share_password.resolve().await?;
+    let mut result = server_api.open_share(share_id, password.as_deref()).await?;
+    if let OpenShareResult::PasswordRequired = result {
+        let password =
+            resolve_password(PasswordSource::Prompt, format!("the share {}", share_id)).await?;
+        result = server_api.open_share(share_id, Some(&password)).await?;
+    }
+    let OpenShareResult::Opened(mut download) = result else {
+        anyhow::bail!("The share {} requires a password", share_id);
+    };
+
+    let share = download.share().clone();
+    log::info!(
+        "Share {} contains {} file(s)",
+        share.name.as_deref().unwrap_or(&share.id),
+        share.files.len()
+    );
+    if let Some(description) = &share.description {
+        log::info!("{}", description);
+    }
+
+    if args.list {
+        let rows = share
+            .files
+            .iter()
+            .map(|file| vec![file.name.clone(), format_size(file.size)])
+            .collect::<Vec<_>>();
+        print!("{}", format_table(&["NAME", "SIZE"], &rows));
+        return Ok(());
+    }
+
+    download.with_callback(output.create_download_handler()?);
+    download.set_skip_existing(args.skip_existing);
+    if args.zip {
+        download.download_zip(&args.target_directory).await?;
+        return Ok(());
+    }
+
+    let files = match files::build_glob_set(&args.files)? {
+        Some(patterns) => share
+            .files
+            .iter()
+            .filter(|file| patterns.is_match(&file.name))
+            .cloned()
+            .collect::<Vec<_>>(),
+        None => share.files.clone(),
+    };
+    if files.is_empty() {
+        anyhow::bail!("There are no files to download");
+    }
+
+    download
+        .download_files(&files, &args.target_directory)
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_share_url;
+
+    #[test]
+    fn test_parse_share_url() {
+        let (server_url, share_id) = parse_share_url("https://share.example.com/s/abc").unwrap();
+        assert_eq!(server_url.as_str(), "https://share.example.com/api/");
+        assert_eq!(share_id, "abc");
+
+        let (server_url, share_id) =
+            parse_share_url("http://example.com:8080/pingvin/share/xyz/?foo=bar").unwrap();
+        assert_eq!(server_url.as_str(), "http://example.com:8080/pingvin/api/");
+        assert_eq!(share_id, "xyz");
+
+        assert!(parse_share_url("abc").is_none());
+        assert!(parse_share_url("https://share.example.com/upload/abc").is_none());
+    }
+}
@@ -0,0 +1,213 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use walkdir::WalkDir;
+
+use crate::api::UploadFile;
+
+/// Options on how directories will be expanded into the files they contain
+#[derive(Debug, Default)]
+pub struct CollectOptions {
+    /// Only upload files matching at least one of these patterns
+    pub include: Vec<String>,
+
+    /// Skip files and directories matching any of these patterns
+    pub exclude: Vec<String>,
+
+    pub follow_symlinks: bool,
+}
+
+pub fn build_glob_set(patterns: &[String]) -> anyhow::Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(Glob::new(pattern).with_context(|| format!("invalid pattern {}", pattern))?);
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// Build the upload name of a file relative to the directory which has been selected.
+/// Path components are always separated by '/', so the share mirrors the folder layout.
+fn relative_name(root_name: &str, relative: &Path) -> String {
+    let mut name = root_name.to_string();
+    for component in relative.components() {
+        name.push('/');
+        name.push_str(&component.as_os_str().to_string_lossy());
+    }
+    name
+}
+
+/// Resolve the given paths into the files which should be uploaded.
+/// Directories will be walked recursively, where include and exclude patterns are matched
+/// against the upload name. Files given explicitly are always uploaded.
+pub fn collect_files(
+    paths: &[PathBuf],
+    options: &CollectOptions,
+) -> anyhow::Result<Vec<UploadFile>> {
+    let include = build_glob_set(&options.include)?;
+    let exclude = build_glob_set(&options.exclude)?;
+
+    let mut files = vec![];
+    for path in paths {
+        if !path.is_dir() {
+            let name = path
+                .file_name()
+                .with_context(|| format!("expected a file name for {}", path.display()))?
+                .to_string_lossy()
+                .to_string();
+
+            files.push(UploadFile {
+                path: path.clone(),
+                name,
+            });
+            continue;
+        }
+
+        let root_name = path
+            .canonicalize()
+            .with_context(|| format!("resolve {}", path.display()))?
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let walker = WalkDir::new(path)
+            .follow_links(options.follow_symlinks)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| {
+                if entry.depth() == 0 {
+                    return true;
+                }
+
+                let relative = entry.path().strip_prefix(path).unwrap_or(entry.path());
+                let name = relative_name(&root_name, relative);
+                !exclude
+                    .as_ref()
+                    .is_some_and(|exclude| exclude.is_match(&name))
+            });
+
+        for entry in walker {
+            let entry = entry.with_context(|| format!("walk {}", path.display()))?;
+            if entry.path_is_symlink() && !options.follow_symlinks {
+                log::debug!("Skipping symlink {}", entry.path().display());
+                continue;
+            }
+
+            if !entry.file_type().is_file() {
+                continue;
+            }
+
+            let relative = entry.path().strip_prefix(path).unwrap_or(entry.path());
+            let name = relative_name(&root_name, relative);
+            if include
+                .as_ref()
+                .is_some_and(|include| !include.is_match(&name))
+            {
+                continue;
+            }
+
+            files.push(UploadFile {
+                path: entry.into_path(),
+                name,
+            });
+        }
+    }
+
+    Ok(files)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{collect_files, CollectOptions};
+
+    #[test]
+    fn test_collect_directory() {
+        let directory = tempfile::tempdir().unwrap();
+        let root = directory.path().join("photos");
+        fs::create_dir_all(root.join("2024/raw")).unwrap();
+        fs::create_dir_all(root.join("cache")).unwrap();
+        fs::write(root.join("2024/img.jpg"), "a").unwrap();
+        fs::write(root.join("2024/raw/img.cr2"), "b").unwrap();
+        fs::write(root.join("cache/thumb.jpg"), "c").unwrap();
+        fs::write(root.join("notes.txt"), "d").unwrap();
+
+        let single_file = directory.path().join("single.txt");
+        fs::write(&single_file, "e").unwrap();
+
+        let files = collect_files(
+            &[root.clone(), single_file.clone()],
+            &CollectOptions::default(),
+        )
+        .unwrap();
+        let names = files
+            .iter()
+            .map(|file| file.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            vec![
+                "photos/2024/img.jpg",
+                "photos/2024/raw/img.cr2",
+                "photos/cache/thumb.jpg",
+                "photos/notes.txt",
+                "single.txt"
+            ]
+        );
+        assert_eq!(files[0].path, root.join("2024/img.jpg"));
+
+        let files = collect_files(
+            std::slice::from_ref(&root),
+            &CollectOptions {
+                include: vec!["*.jpg".to_string()],
+                exclude: vec!["photos/cache".to_string()],
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let names = files
+            .iter()
+            .map(|file| file.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["photos/2024/img.jpg"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_collect_symlinks() {
+        let directory = tempfile::tempdir().unwrap();
+        let root = directory.path().join("root");
+        let target = directory.path().join("target");
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(&target).unwrap();
+        fs::write(root.join("file.txt"), "a").unwrap();
+        fs::write(target.join("linked.txt"), "b").unwrap();
+        std::os::unix::fs::symlink(&target, root.join("link")).unwrap();
+
+        let files = collect_files(std::slice::from_ref(&root), &CollectOptions::default()).unwrap();
+        let names = files
+            .iter()
+            .map(|file| file.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["root/file.txt"]);
+
+        let files = collect_files(
+            std::slice::from_ref(&root),
+            &CollectOptions {
+                follow_symlinks: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let names = files
+            .iter()
+            .map(|file| file.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["root/file.txt", "root/link/linked.txt"]);
+    }
+}
@@ -1,32 +1,64 @@
 use std::env;
 
 use anyhow::Context;
-use log::LevelFilter;
+use log::{LevelFilter, Record};
 use log4rs::{
-    append::console::ConsoleAppender,
+    append::{
+        console::{ConsoleAppender, Target},
+        Append,
+    },
     config::{Appender, Root},
     encode::pattern::PatternEncoder,
     Config,
 };
 
-pub fn init() -> anyhow::Result<()> {
+use crate::output;
+
+/// Removes the progress bars before writing a record,
+/// otherwise the next progress update would overwrite it.
+#[derive(Debug)]
+struct ProgressAwareAppender(ConsoleAppender);
+
+impl Append for ProgressAwareAppender {
+    fn append(&self, record: &Record) -> anyhow::Result<()> {
+        output::clear_bars();
+        self.0.append(record)
+    }
+
+    fn flush(&self) {
+        self.0.flush();
+    }
+}
+
+/// Attach to the console of the parent process, so anything printed shows up within it.
+/// Needs to be called before anything is printed, including the help and argument errors.
+pub fn attach_console() {
     #[cfg(target_family = "windows")]
     unsafe {
         use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
         let _ = AttachConsole(ATTACH_PARENT_PROCESS);
     };
+}
 
+/// Initialize the logger.
+/// Logs are written to stderr instead of stdout if stdout is reserved for the output.
+pub fn init(log_to_stderr: bool) -> anyhow::Result<()> {
     let config_path = env::current_exe()?
         .parent()
         .context("missing exe parent")?
         .to_owned()
         .join("log4rs.yml");
 
-    println!(
+    let message = format!(
         "> log4rs config path: {}.\n> Logging enabled: {}",
         config_path.display(),
         if config_path.exists() { "yes" } else { "no" }
     );
+    if log_to_stderr {
+        eprintln!("{}", message);
+    } else {
+        println!("{}", message);
+    }
     if config_path.exists() {
         log4rs::init_file(config_path, Default::default())?;
     } else {
@@ -34,11 +66,16 @@ pub fn init() -> anyhow::Result<()> {
             .appender(
                 Appender::builder().build(
                     "console",
-                    Box::new(
+                    Box::new(ProgressAwareAppender(
                         ConsoleAppender::builder()
+                            .target(if log_to_stderr {
+                                Target::Stderr
+                            } else {
+                                Target::Stdout
+                            })
                             .encoder(Box::new(PatternEncoder::new("{h({l}): <5.5} - {m}{n}")))
                             .build(),
-                    ),
+                    )),
                 ),
             )
             .build(Root::builder().appender("console").build(LevelFilter::Info))?;
@@ -1,27 +1,109 @@
 #![cfg_attr(target_family = "windows", windows_subsystem = "windows")]
 
 use anyhow::Context;
-use api::{ExpireDuration, PingvinApi};
-use clap::Parser;
+use api::{
+    ExpireDuration, LoginResult, OAuthAuthenticator, PasswordAuthenticator, PingvinApi,
+    PublicConfiguration, RetryPolicy, SessionCache, ShareSecurityOptions, TokenAuthenticator,
+    UploadJournal, UploadStream,
+};
+use archive::ArchiveFormat;
+use clap::{Parser, Subcommand};
+use clipboard::CopyLink;
+use config::{ClientConfig, Profile};
+use credentials::PasswordSource;
+use download::DownloadArgs;
 use output::{AppOutput, OutputType};
 use reqwest::Url;
-use std::{path::PathBuf, process::ExitCode};
+use reverse_shares::ReverseSharesAction;
+use shares::SharesAction;
+use std::{path::PathBuf, process::ExitCode, time::Duration};
 
 mod api;
+mod archive;
+mod browser;
+mod clipboard;
+mod config;
+mod credentials;
+mod download;
+mod files;
 mod logger;
 mod output;
+mod recipients;
+mod reverse_shares;
+mod shares;
+mod totp;
 
 /// CLI tool to upload files to a pinving share instance
 #[derive(Debug, Parser)]
+#[command(subcommand_negates_reqs = true)]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// The server URL of the pingvin share to upload the files to.
-    #[arg(short, long, value_parser = Url::parse)]
-    pub server_url: Url,
+    /// The link of a reverse share (e.g. https://share.example.com/upload/abc) uploads into it without signing in.
+    #[arg(short, long, global = true, value_parser = Url::parse)]
+    pub server_url: Option<Url>,
+
+    /// Use the server, credentials and defaults of a profile from the config file
+    #[arg(short, long, global = true)]
+    pub profile: Option<String>,
+
+    /// The user to login with.
+    /// Takes precedence over the user within the server URL or the selected profile.
+    #[arg(short, long, global = true)]
+    pub username: Option<String>,
+
+    /// Read the password from the given environment variable
+    #[arg(long, global = true, group = "password")]
+    pub password_env: Option<String>,
+
+    /// Read the password from the given file
+    #[arg(long, global = true, group = "password")]
+    pub password_file: Option<PathBuf>,
+
+    /// Take the password from the OS keyring (see `pingvin-cli keyring set`)
+    #[arg(long, global = true, group = "password")]
+    pub password_keyring: bool,
+
+    /// Ask for the password on the terminal
+    #[arg(long, global = true, group = "password")]
+    pub password_prompt: bool,
+
+    /// The current code of the authenticator app for users with two-factor authentication.
+    /// The code will be asked for if required and not given.
+    #[arg(long, global = true)]
+    pub totp: Option<String>,
+
+    /// Always sign in with the password instead of reusing the cached login session
+    #[arg(long, global = true)]
+    pub no_session_cache: bool,
+
+    /// Authenticate with the refresh token read from the given environment variable instead of signing in.
+    /// The token is the value of the 'refresh_token' cookie of a signed in browser session
+    /// and stays valid for the session duration configured on the server (3 months by default).
+    /// The token is read from PINGVIN_TOKEN by default, if it is set and no user has been given.
+    #[arg(long, global = true)]
+    pub token_env: Option<String>,
 
     /// A list of files which should be uploaded.
+    /// Directories will be uploaded recursively.
+    /// Use '-' to upload the data read from stdin.
     #[arg(short, long, required = true)]
     pub files: Vec<PathBuf>,
 
+    /// The file name used for the data read from stdin
+    #[arg(long, default_value = "stdin")]
+    pub stdin_name: String,
+
+    #[command(flatten)]
+    pub file_selection: FileSelectionArgs,
+
+    /// Pack all selected files into a single archive which is created while uploading.
+    /// The archive is named after the share name or the common parent folder of the selected files.
+    #[arg(long, value_enum)]
+    pub archive: Option<ArchiveFormat>,
+
     /// Specify the id the share should create. If a share if that id already exists the file upload will fail.
     #[arg(long)]
     pub id: Option<String>,
@@ -41,30 +123,315 @@ pub struct Args {
     #[arg(short, long, verbatim_doc_comment)]
     pub expire_duration: Option<ExpireDuration>,
 
-    /// Change the output type on how process indication will be done
-    #[arg(short, long, value_enum, default_value_t = OutputType::Console)]
-    pub output: OutputType,
+    /// Reduce the expiration to the maximum expiration allowed by the server
+    /// instead of failing if it is exceeded
+    #[arg(long)]
+    pub clamp_expiration: bool,
+
+    #[command(flatten)]
+    pub share_password: SharePasswordArgs,
+
+    /// Maximum amount of times the share can be viewed
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_views: Option<u32>,
+
+    /// Notify the given email address about the share.
+    /// Can be specified multiple times. Requires email recipients to be enabled on the server.
+    #[arg(long = "recipient", value_name = "EMAIL")]
+    pub recipients: Vec<String>,
+
+    /// Read additional recipients from the given file (one address per line)
+    #[arg(long)]
+    pub recipients_file: Option<PathBuf>,
+
+    /// Upload into the reverse share of the given token instead of signing in
+    #[arg(long)]
+    pub reverse_share_token: Option<String>,
+
+    /// Copy a link of the share to the clipboard once the upload completed.
+    /// Default: the share page
+    #[arg(long, value_enum, value_name = "LINK", num_args = 0..=1, default_missing_value = "share")]
+    pub copy: Option<CopyLink>,
+
+    /// Change the output type on how process indication will be done.
+    /// Default: 'console'
+    #[arg(short, long, global = true, value_enum)]
+    pub output: Option<OutputType>,
+
+    /// Do not record the upload progress within the upload journal.  
+    /// Interrupted uploads can not be resumed without the journal.
+    #[arg(long, verbatim_doc_comment)]
+    pub no_journal: bool,
+
+    /// Amount of files which will be uploaded in parallel
+    #[arg(long, global = true, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+    pub parallel: u16,
+
+    /// Maximum attempts to upload a single chunk.  
+    /// Only transient failures like server errors or connection resets will be retried.
+    #[arg(long, global = true, default_value_t = 5, verbatim_doc_comment)]
+    pub retry_attempts: u32,
+
+    /// Delay in milliseconds before retrying a failed chunk.
+    /// The delay doubles with every attempt.
+    #[arg(long, global = true, default_value_t = 1_000)]
+    pub retry_delay: u64,
+
+    /// Maximum delay in milliseconds between two attempts
+    #[arg(long, global = true, default_value_t = 30_000)]
+    pub retry_max_delay: u64,
+
+    /// Seconds without any upload progress after which a chunk upload is aborted and retried.
+    /// Slow uploads are not aborted as long as data keeps being sent.
+    #[arg(long, global = true, default_value_t = 300, value_parser = clap::value_parser!(u64).range(1..))]
+    pub idle_timeout: u64,
+}
+
+impl Args {
+    /// The server URL given on the command line or by the selected profile
+    pub fn server_url(&self, profile: &Profile) -> anyhow::Result<Option<Url>> {
+        match &self.server_url {
+            Some(server_url) => Ok(Some(server_url.clone())),
+            None => profile.server_url(),
+        }
+    }
+
+    /// The user given on the command line, within the server URL or by the selected profile
+    pub fn username(&self, server_url: &Url, profile: &Profile) -> Option<String> {
+        self.username
+            .clone()
+            .or_else(|| match server_url.username() {
+                "" => None,
+                username => Some(username.to_string()),
+            })
+            .or_else(|| profile.username.clone())
+    }
+
+    pub fn password_source(&self) -> Option<PasswordSource> {
+        if let Some(name) = &self.password_env {
+            Some(PasswordSource::Env(name.clone()))
+        } else if let Some(path) = &self.password_file {
+            Some(PasswordSource::File(path.clone()))
+        } else if self.password_keyring {
+            Some(PasswordSource::Keyring)
+        } else if self.password_prompt {
+            Some(PasswordSource::Prompt)
+        } else {
+            None
+        }
+    }
+
+    /// The API token read from the selected environment variable.
+    /// PINGVIN_TOKEN is only used if no user has been given, so explicit credentials take precedence.
+    pub fn api_token(&self, has_username: bool) -> anyhow::Result<Option<String>> {
+        match &self.token_env {
+            Some(name) => std::env::var(name).map(Some).with_context(|| {
+                format!("read the API token from the environment variable {}", name)
+            }),
+            None if has_username => Ok(None),
+            None => Ok(std::env::var(TOKEN_ENV)
+                .ok()
+                .filter(|token| !token.is_empty())),
+        }
+    }
+
+    /// The password protection and view limit of the share
+    pub async fn share_security(&self) -> anyhow::Result<ShareSecurityOptions> {
+        let password = self.share_password.resolve().await?;
+        ShareSecurityOptions::new(password, self.max_views.map(|value| value as usize))
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.retry_attempts.max(1),
+            base_delay: Duration::from_millis(self.retry_delay),
+            max_delay: Duration::from_millis(self.retry_max_delay),
+            idle_timeout: Duration::from_secs(self.idle_timeout),
+        }
+    }
+}
+
+/// Which files of the selected directories will be uploaded
+#[derive(Debug, clap::Args)]
+pub struct FileSelectionArgs {
+    /// Only upload files found within directories which match the given glob pattern.
+    /// The pattern is matched against the path relative to the selected directory's parent (e.g. 'photos/**/*.jpg').
+    #[arg(long)]
+    pub include: Vec<String>,
+
+    /// Skip files and directories matching the given glob pattern
+    #[arg(long)]
+    pub exclude: Vec<String>,
+
+    /// Follow symbolic links while walking directories instead of skipping them
+    #[arg(long)]
+    pub follow_symlinks: bool,
+}
+
+impl FileSelectionArgs {
+    pub fn collect_options(&self) -> files::CollectOptions {
+        files::CollectOptions {
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+            follow_symlinks: self.follow_symlinks,
+        }
+    }
+}
+
+/// Password of a share to create or to download
+#[derive(Debug, clap::Args)]
+pub struct SharePasswordArgs {
+    /// The password of the share.
+    /// Prefer the other share password options, as arguments are visible to other processes.
+    #[arg(long, group = "share_password_source")]
+    pub share_password: Option<String>,
+
+    /// Read the share password from the given environment variable
+    #[arg(long, group = "share_password_source")]
+    pub share_password_env: Option<String>,
+
+    /// Read the share password from the given file
+    #[arg(long, group = "share_password_source")]
+    pub share_password_file: Option<PathBuf>,
+
+    /// Ask for the share password on the terminal
+    #[arg(long, group = "share_password_source")]
+    pub share_password_prompt: bool,
+}
+
+impl SharePasswordArgs {
+    pub fn source(&self) -> Option<PasswordSource> {
+        if let Some(name) = &self.share_password_env {
+            Some(PasswordSource::Env(name.clone()))
+        } else if let Some(path) = &self.share_password_file {
+            Some(PasswordSource::File(path.clone()))
+        } else if self.share_password_prompt {
+            Some(PasswordSource::Prompt)
+        } else {
+            None
+        }
+    }
+
+    /// The password given on the command line or read from the selected source
+    pub async fn resolve(&self) -> anyhow::Result<Option<String>> {
+        match (&self.share_password, self.source()) {
+            (Some(password), _) => Ok(Some(password.clone())),
+            (None, Some(source)) => Ok(Some(
+                resolve_password(source, "the share".to_string()).await?,
+            )),
+            (None, None) => Ok(None),
+        }
+    }
+}
+
+/// File path which selects stdin instead of a file
+const STDIN_PATH: &str = "-";
+
+/// Environment variable the API token is read from by default
+const TOKEN_ENV: &str = "PINGVIN_TOKEN";
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Resume interrupted uploads recorded in the upload journal
+    Resume {
+        /// The id of the share to resume.
+        /// All pending uploads will be resumed if omitted.
+        share_id: Option<String>,
+    },
+
+    /// Manage the passwords stored within the OS keyring
+    Keyring {
+        #[command(subcommand)]
+        action: KeyringAction,
+    },
+
+    /// Sign in and store the login session within the session cache
+    Login {
+        /// Sign in within the browser using the given OAuth provider (e.g. github, google, oidc).
+        ///
+        /// Limitation: Pingvin Share can not redirect an OAuth login back to the CLI,
+        /// it always stores the login session within the cookies of the browser.
+        /// Hence there is no automatic callback: after signing in, open the developer tools
+        /// of the browser, copy the value of the 'refresh_token' cookie of the server
+        /// and paste it when asked for the refresh token.
+        #[arg(long, value_name = "PROVIDER")]
+        oauth: Option<String>,
+    },
+
+    /// Download the files of a share
+    Download(DownloadArgs),
+
+    /// Manage the shares of the signed in user
+    Shares {
+        #[command(subcommand)]
+        action: SharesAction,
+    },
+
+    /// Manage the reverse shares others can upload shares with
+    ReverseShares {
+        #[command(subcommand)]
+        action: ReverseSharesAction,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum KeyringAction {
+    /// Store the password of the user for the server.
+    /// The password will be asked for unless another password source has been given.
+    Set,
+
+    /// Remove the stored password of the user for the server
+    Delete,
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<ExitCode> {
-    logger::init()?;
-
+    logger::attach_console();
     let args = match Args::try_parse() {
         Ok(args) => args,
         Err(err) => {
-            eprintln!("{}", err);
-            return Ok(ExitCode::FAILURE);
+            /* the help and version are printed to stdout, errors to stderr */
+            let _ = err.print();
+            return Ok(match err.use_stderr() {
+                true => ExitCode::FAILURE,
+                false => ExitCode::SUCCESS,
+            });
         }
     };
 
-    let output = output::create(args.output)?;
-    let result = execute_upload(&args, &*output).await;
-    if let Err(err) = result {
-        output.show_upload_error(&err);
+    let profile = ClientConfig::load(&config::config_path()?)?.profile(args.profile.as_deref())?;
+    let output_type = args
+        .output
+        .or(profile.output)
+        .unwrap_or(OutputType::Console);
+    logger::init(output_type == OutputType::Json)?;
+
+    let output = output::create(output_type)?;
+    let result = match &args.command {
+        None => execute_upload(&args, &profile, &*output).await,
+        Some(Command::Resume { share_id }) => {
+            execute_resume(&args, &profile, share_id.as_deref(), &*output).await
+        }
+        Some(Command::Keyring { action }) => execute_keyring(&args, &profile, action).await,
+        Some(Command::Login { oauth }) => execute_login(&args, &profile, oauth.as_deref()).await,
+        Some(Command::Download(download_args)) => {
+            execute_download(&args, &profile, download_args, &*output).await
+        }
+        Some(Command::Shares { action }) => execute_shares(&args, &profile, action, &*output).await,
+        Some(Command::ReverseShares { action }) => {
+            execute_reverse_shares(&args, &profile, action).await
+        }
+    };
+    if let Err(err) = &result {
+        output.show_upload_error(err);
+    }
+    output.finish();
 
-        return match args.output {
-            OutputType::Console => Ok(ExitCode::FAILURE),
+    if result.is_err() {
+        return match output_type {
+            OutputType::Console | OutputType::Json | OutputType::DesktopNotification => {
+                Ok(ExitCode::FAILURE)
+            }
 
             /* Return success, so the context menu handler does not show an additional popup */
             OutputType::WindowsNotification => Ok(ExitCode::SUCCESS),
@@ -74,30 +441,224 @@ async fn main() -> anyhow::Result<ExitCode> {
     Ok(ExitCode::SUCCESS)
 }
 
-async fn execute_upload(args: &Args, output: &dyn AppOutput) -> anyhow::Result<()> {
-    let mut server_api = PingvinApi::new(args.server_url.clone())?;
+/// Resolve the password from the given source.
+/// Reading the password may block, e.g. while prompting for it or unlocking the keyring.
+async fn resolve_password(source: PasswordSource, account: String) -> anyhow::Result<String> {
+    tokio::task::spawn_blocking(move || source.resolve(&account, &*credentials::default_keyring()))
+        .await?
+}
 
-    log::info!("Fetching server config");
-    let server_config = server_api.public_config().await.context("server config")?;
+/// Restore the cached session of the user, refreshing its access token if required.
+/// Returns false if the session needs to be established by signing in again.
+async fn restore_session(
+    server_api: &mut PingvinApi,
+    session_cache: &mut SessionCache,
+    cache_key: &str,
+) -> anyhow::Result<bool> {
+    let Some(session) = session_cache.get(cache_key) else {
+        return Ok(false);
+    };
+
+    server_api.set_session(session.clone())?;
+    if server_api.validate_session().await? {
+        log::info!("Reusing the cached login session.");
+        return Ok(true);
+    }
+
+    if server_api.refresh_session().await? {
+        log::info!("Refreshed the cached login session.");
+        if let Some(session) = server_api.session() {
+            session_cache.insert(cache_key.to_string(), session.clone());
+        }
+        return Ok(true);
+    }
+
+    log::info!("The cached login session expired.");
+    session_cache.remove(cache_key);
+    Ok(false)
+}
+
+/// The TOTP code given on the command line, generated from the profile secret or asked for
+async fn totp_code(args: &Args, profile: &Profile) -> anyhow::Result<String> {
+    if let Some(code) = &args.totp {
+        return Ok(code.clone());
+    }
+
+    if let Some(secret) = &profile.totp_secret {
+        return totp::current_code(secret);
+    }
+
+    tokio::task::spawn_blocking(credentials::prompt_totp).await?
+}
+
+async fn authenticate(
+    server_api: &mut PingvinApi,
+    server_config: &PublicConfiguration,
+    server_url: &Url,
+    args: &Args,
+    profile: &Profile,
+) -> anyhow::Result<()> {
+    let username = args.username(server_url, profile);
+    if let Some(token) = args.api_token(username.is_some())? {
+        log::info!("Authenticating with the API token.");
+        let result = server_api
+            .authenticate(&TokenAuthenticator { token })
+            .await?;
+        if result != LoginResult::LoggedIn {
+            anyhow::bail!(
+                "The server rejected the API token, it may have expired or been revoked."
+            );
+        }
+        return Ok(());
+    }
 
     let allow_unauthenticated_shares = server_config
         .get_bool("share.allowUnauthenticatedShares")
         .unwrap_or(false);
 
-    if !allow_unauthenticated_shares || !args.server_url.username().is_empty() {
-        let username = args.server_url.username();
-        let Some(password) = args.server_url.password() else {
-            anyhow::bail!("Unauthenticated shares are not allowed.\nPlease provide a user and a password within the server URL");
+    if !allow_unauthenticated_shares || username.is_some() {
+        let Some(username) = username else {
+            anyhow::bail!("Unauthenticated shares are not allowed.\nPlease provide a user (--username) and a password source (e.g. --password-prompt)");
+        };
+
+        let cache_key = SessionCache::key(server_url, &username);
+        let mut session_cache = if args.no_session_cache {
+            None
+        } else {
+            match SessionCache::load(&api::session_cache_path()?) {
+                Ok(cache) => Some(cache),
+                Err(err) => {
+                    log::warn!("Failed to load the session cache: {:#}", err);
+                    None
+                }
+            }
         };
 
-        if username.is_empty() {
-            anyhow::bail!("Unauthenticated shares are not allowed.\nPlease provide a user and a password within the server URL");
+        if let Some(session_cache) = &mut session_cache {
+            match restore_session(server_api, session_cache, &cache_key).await {
+                Ok(true) => {
+                    if let Err(err) = session_cache.save() {
+                        log::warn!("Failed to update the session cache: {:#}", err);
+                    }
+                    return Ok(());
+                }
+                Ok(false) => {}
+                Err(err) => log::warn!("Failed to restore the cached session: {:#}", err),
+            }
         }
 
+        if server_config
+            .get_bool("oauth.disablePassword")
+            .unwrap_or(false)
+        {
+            anyhow::bail!("The server does not allow signing in with a password.\nPlease sign in using `pingvin-cli login --oauth <provider>` or provide an API token (--token-env or {})", TOKEN_ENV);
+        }
+
+        let password_source = args.password_source();
+        let password = match (password_source, server_url.password()) {
+            (None, Some(password)) => {
+                log::warn!("The password within the server URL is visible to other processes. Consider using --password-env, --password-file or --password-keyring instead.");
+                password.to_string()
+            }
+            (source, _) => match source.or_else(|| profile.password_source()) {
+                Some(source) => {
+                    let account = credentials::keyring_account(server_url, &username);
+                    resolve_password(source, account).await?
+                }
+                None => anyhow::bail!("Unauthenticated shares are not allowed.\nPlease provide a password source (e.g. --password-prompt) for {}", username),
+            },
+        };
+
         log::info!("Try to login with given credentials.");
-        if !server_api.login(username, password).await? {
+        let mut result = server_api
+            .authenticate(&PasswordAuthenticator { username, password })
+            .await?;
+        if let LoginResult::TotpRequired { login_token } = &result {
+            log::info!("Completing the login with a TOTP code.");
+            let totp = totp_code(args, profile).await?;
+            result = server_api.login_totp(login_token, &totp).await?;
+        }
+
+        if result != LoginResult::LoggedIn {
             anyhow::bail!("Failed to login with the given credentials.");
         }
+
+        if let (Some(session_cache), Some(session)) = (&mut session_cache, server_api.session()) {
+            session_cache.insert(cache_key, session.clone());
+            if let Err(err) = session_cache.save() {
+                log::warn!("Failed to update the session cache: {:#}", err);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn execute_upload(
+    args: &Args,
+    profile: &Profile,
+    output: &dyn AppOutput,
+) -> anyhow::Result<()> {
+    let (server_url, reverse_share_token) = match args
+        .server_url
+        .as_ref()
+        .and_then(|server_url| api::parse_frontend_url(server_url, &["upload"]))
+    {
+        Some((server_url, token)) => (server_url, Some(token)),
+        None => (
+            args.server_url(profile)?
+                .context("missing server url (--server-url or --profile)")?,
+            args.reverse_share_token.clone(),
+        ),
+    };
+
+    let read_stdin = args.files.iter().any(|path| path.as_os_str() == STDIN_PATH);
+    let paths = args
+        .files
+        .iter()
+        .filter(|path| path.as_os_str() != STDIN_PATH)
+        .cloned()
+        .collect::<Vec<_>>();
+    if read_stdin && args.archive.is_some() {
+        anyhow::bail!("Data read from stdin can not be added to an archive");
+    }
+
+    let files = files::collect_files(&paths, &args.file_selection.collect_options())?;
+    if files.is_empty() && !read_stdin {
+        anyhow::bail!("There are no files to upload");
+    }
+    let security = args.share_security().await?;
+    let recipients =
+        recipients::collect_recipients(&args.recipients, args.recipients_file.as_deref())?;
+
+    let mut server_api = PingvinApi::new(server_url.clone())?;
+
+    log::info!("Fetching server config");
+    let server_config = server_api.public_config().await.context("server config")?;
+    match &reverse_share_token {
+        Some(token) => {
+            let reverse_share = server_api.reverse_share(token).await?;
+            if args.archive.is_some() || read_stdin {
+                /* the size of archives and stdin is unknown until they have been uploaded */
+                log::warn!(
+                    "Skipping the size check, the server rejects uploads exceeding the maximum size of {} allowed by the reverse share",
+                    shares::format_size(reverse_share.max_share_size)
+                );
+            } else {
+                let total_size = files
+                    .iter()
+                    .map(|file| file.path.metadata().map(|metadata| metadata.len()))
+                    .sum::<std::io::Result<u64>>()?;
+                if total_size > reverse_share.max_share_size {
+                    anyhow::bail!(
+                        "The files exceed the maximum size of {} allowed by the reverse share",
+                        shares::format_size(reverse_share.max_share_size)
+                    );
+                }
+            }
+            server_api.set_reverse_share_token(token)?;
+        }
+        None => authenticate(&mut server_api, &server_config, &server_url, args, profile).await?,
     }
 
     let mut share_builder = server_api.create_share();
@@ -110,12 +671,333 @@ async fn execute_upload(args: &Args, output: &dyn AppOutput) -> anyhow::Result<(
     if let Some(value) = &args.description {
         share_builder.set_description(value.to_string());
     }
-    for file in &args.files {
-        share_builder.add_file(file.to_owned());
+    if !security.is_empty() {
+        share_builder.set_security_options(security);
+    }
+    for recipient in recipients {
+        share_builder.add_recipient(recipient);
     }
+    if reverse_share_token.is_some() {
+        /* the server applies the expiration of the reverse share */
+        if args.expire_duration.is_some() {
+            log::warn!("The expiration is ignored, as the reverse share defines it");
+        }
+    } else {
+        let expiration = args
+            .expire_duration
+            .or(profile.expiration)
+            .unwrap_or(ExpireDuration::Never)
+            .limit(server_config.max_expiration(), args.clamp_expiration)?;
+        share_builder.set_expiration(expiration);
+    }
+
+    let mut archive_worker = None;
+    if let Some(format) = args.archive {
+        let archive_name = match &args.name {
+            Some(name) => format!("{}.{}", name, format.extension()),
+            None => archive::archive_name(&paths, format),
+        };
+
+        log::info!("Uploading {} files as {}", files.len(), archive_name);
+        let (reader, worker) = archive::create_archive_stream(format, files);
+        share_builder.add_stream(UploadStream {
+            name: archive_name,
+            reader: Box::new(reader),
+        });
+        archive_worker = Some(worker);
+    } else {
+        for file in files {
+            share_builder.add_upload_file(file);
+        }
+    }
+    if read_stdin {
+        share_builder.add_stream(UploadStream {
+            name: args.stdin_name.clone(),
+            reader: Box::new(tokio::io::stdin()),
+        });
+    }
+    /* resuming requires the reverse share token, which has been used up by then */
+    if !args.no_journal && reverse_share_token.is_none() {
+        share_builder.set_journal_directory(api::journal_directory()?);
+    }
+    share_builder.set_retry_policy(args.retry_policy());
+    share_builder.set_parallel_uploads(args.parallel as usize);
 
     share_builder.with_callback(output.create_upload_handler(&server_config)?);
 
-    let _ = share_builder.upload().await?;
+    let share = share_builder.upload().await?;
+    if let Some(worker) = archive_worker {
+        worker.await?.context("create archive")?;
+    }
+
+    if let Some(link) = args.copy {
+        if let Err(err) = clipboard::system().and_then(|clipboard| {
+            clipboard::copy_link(&*clipboard, link, server_config.app_url(), &share)
+        }) {
+            log::warn!("Failed to copy URL to clipboard: {:#}", err);
+        }
+    }
+    Ok(())
+}
+
+async fn execute_resume(
+    args: &Args,
+    profile: &Profile,
+    share_id: Option<&str>,
+    output: &dyn AppOutput,
+) -> anyhow::Result<()> {
+    let journal_directory = api::journal_directory()?;
+    let journals = match share_id {
+        Some(share_id) => vec![UploadJournal::find(&journal_directory, share_id)?],
+        None => UploadJournal::list(&journal_directory)?,
+    };
+
+    if journals.is_empty() {
+        log::info!("There are no pending uploads to resume.");
+        return Ok(());
+    }
+
+    for journal in journals {
+        let journal_url = journal.server_url()?;
+        let server_url = match args.server_url(profile)? {
+            /* the journal never contains credentials, use the ones provided with the server url */
+            Some(server_url) if server_url.host_str() == journal_url.host_str() => server_url,
+            Some(_) => {
+                log::info!(
+                    "Skipping share {} as it has been uploaded to {}",
+                    journal.state().share_id,
+                    journal_url
+                );
+                continue;
+            }
+            None => journal_url,
+        };
+
+        let mut server_api = PingvinApi::new(server_url.clone())?;
+        let server_config = server_api.public_config().await.context("server config")?;
+        authenticate(&mut server_api, &server_config, &server_url, args, profile).await?;
+
+        log::info!("Resuming upload of share {}", journal.state().share_id);
+        let mut share_builder = server_api.resume_share(journal);
+        share_builder.set_retry_policy(args.retry_policy());
+        share_builder.set_parallel_uploads(args.parallel as usize);
+        share_builder.with_callback(output.create_upload_handler(&server_config)?);
+        let _ = share_builder.upload().await?;
+    }
+
+    Ok(())
+}
+
+async fn execute_keyring(
+    args: &Args,
+    profile: &Profile,
+    action: &KeyringAction,
+) -> anyhow::Result<()> {
+    let server_url = args
+        .server_url(profile)?
+        .context("missing server url (--server-url or --profile)")?;
+    let username = args
+        .username(&server_url, profile)
+        .context("missing user (--username)")?;
+    let account = credentials::keyring_account(&server_url, &username);
+
+    match action {
+        KeyringAction::Set => {
+            let source = match args.password_source() {
+                None | Some(PasswordSource::Keyring) => PasswordSource::Prompt,
+                Some(source) => source,
+            };
+
+            let password = resolve_password(source, account.clone()).await?;
+            tokio::task::spawn_blocking({
+                let account = account.clone();
+                move || credentials::default_keyring().set_password(&account, &password)
+            })
+            .await??;
+            log::info!("Stored the password for {} within the keyring", account);
+        }
+        KeyringAction::Delete => {
+            let deleted = tokio::task::spawn_blocking({
+                let account = account.clone();
+                move || credentials::default_keyring().delete_password(&account)
+            })
+            .await??;
+
+            if deleted {
+                log::info!("Removed the password for {} from the keyring", account);
+            } else {
+                log::info!("There is no password stored for {}", account);
+            }
+        }
+    }
+
     Ok(())
 }
+
+async fn execute_login(args: &Args, profile: &Profile, oauth: Option<&str>) -> anyhow::Result<()> {
+    let server_url = args
+        .server_url(profile)?
+        .context("missing server url (--server-url or --profile)")?;
+    let mut server_api = PingvinApi::new(server_url.clone())?;
+
+    let Some(provider) = oauth else {
+        let server_config = server_api.public_config().await.context("server config")?;
+        authenticate(&mut server_api, &server_config, &server_url, args, profile).await?;
+        if !server_api.is_authenticated() {
+            anyhow::bail!(
+                "Please provide a user (--username) and a password source (e.g. --password-prompt)"
+            );
+        }
+        log::info!("Signed in successfully.");
+        return Ok(());
+    };
+
+    let providers = server_api
+        .oauth_providers()
+        .await
+        .context("OAuth providers")?;
+    if !providers.iter().any(|value| value == provider) {
+        anyhow::bail!(
+            "The server does not offer signing in with {}. Available providers: {}",
+            provider,
+            providers.join(", ")
+        );
+    }
+
+    let result = server_api
+        .authenticate(&OAuthAuthenticator {
+            provider: provider.to_string(),
+            open_browser: Box::new(|url| {
+                log::info!("Continue the login within the browser: {}", url);
+                log::info!("Afterwards copy the value of the 'refresh_token' cookie of the server from the developer tools of the browser.");
+                if let Err(err) = browser::open(url.as_str()) {
+                    log::warn!("Failed to open the browser: {:#}", err);
+                }
+                Ok(())
+            }),
+            read_refresh_token: Box::new(credentials::prompt_refresh_token),
+        })
+        .await?;
+    if result != LoginResult::LoggedIn {
+        anyhow::bail!("The login with {} failed.", provider);
+    }
+
+    let username = server_api.current_user().await?.username;
+    let session = server_api.session().context("missing login session")?;
+    let mut session_cache = SessionCache::load(&api::session_cache_path()?)?;
+    session_cache.insert(SessionCache::key(&server_url, &username), session.clone());
+    session_cache.save()?;
+
+    log::info!(
+        "Signed in as {}. Use --username {} to reuse the login session.",
+        username,
+        username
+    );
+    Ok(())
+}
+
+async fn execute_download(
+    args: &Args,
+    profile: &Profile,
+    download_args: &DownloadArgs,
+    output: &dyn AppOutput,
+) -> anyhow::Result<()> {
+    let (server_url, share_id) = match download::parse_share_url(&download_args.share) {
+        /* the server URL given explicitly takes precedence for servers not serving the API at /api */
+        Some((server_url, share_id)) => (args.server_url.clone().unwrap_or(server_url), share_id),
+        None => (
+            args.server_url(profile)?
+                .context("missing server url (--server-url or --profile)")?,
+            download_args.share.clone(),
+        ),
+    };
+
+    let server_api = PingvinApi::new(server_url)?;
+    download::download(&server_api, &share_id, download_args, output).await
+}
+
+async fn execute_shares(
+    args: &Args,
+    profile: &Profile,
+    action: &SharesAction,
+    output: &dyn AppOutput,
+) -> anyhow::Result<()> {
+    let server_url = args
+        .server_url(profile)?
+        .context("missing server url (--server-url or --profile)")?;
+
+    let mut server_api = PingvinApi::new(server_url.clone())?;
+    let server_config = server_api.public_config().await.context("server config")?;
+    authenticate(&mut server_api, &server_config, &server_url, args, profile).await?;
+    if !server_api.is_authenticated() {
+        anyhow::bail!("Managing shares requires a login.\nPlease provide a user (--username) and a password source (e.g. --password-prompt) or an API token");
+    }
+
+    match action {
+        SharesAction::List(list_args) => shares::list(&server_api, &server_config, list_args).await,
+        SharesAction::Delete(delete_args) => {
+            shares::delete(&server_api, &server_config, delete_args).await
+        }
+        SharesAction::Add(add_args) => {
+            shares::add(&server_api, &server_config, args, add_args, output).await
+        }
+        SharesAction::RmFile(rm_file_args) => shares::remove_file(&server_api, rm_file_args).await,
+        SharesAction::Replace(replace_args) => {
+            shares::replace(&server_api, &server_config, args, replace_args, output).await
+        }
+    }
+}
+
+async fn execute_reverse_shares(
+    args: &Args,
+    profile: &Profile,
+    action: &ReverseSharesAction,
+) -> anyhow::Result<()> {
+    let server_url = args
+        .server_url(profile)?
+        .context("missing server url (--server-url or --profile)")?;
+
+    let mut server_api = PingvinApi::new(server_url.clone())?;
+    let server_config = server_api.public_config().await.context("server config")?;
+    authenticate(&mut server_api, &server_config, &server_url, args, profile).await?;
+    if !server_api.is_authenticated() {
+        anyhow::bail!("Managing reverse shares requires a login.\nPlease provide a user (--username) and a password source (e.g. --password-prompt) or an API token");
+    }
+
+    match action {
+        ReverseSharesAction::Create(create_args) => {
+            reverse_shares::create(&server_api, &server_config, create_args).await
+        }
+        ReverseSharesAction::List => reverse_shares::list(&server_api).await,
+        ReverseSharesAction::Delete { ids } => reverse_shares::delete(&server_api, ids).await,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use clap::{CommandFactory, Parser};
+
+    use super::{Args, TOKEN_ENV};
+
+    #[test]
+    fn test_args() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn test_api_token() {
+        std::env::set_var(TOKEN_ENV, "ci-token");
+
+        /* explicit credentials take precedence over the default variable */
+        let args = Args::try_parse_from(["pingvin-cli", "-f", "notes.txt"]).unwrap();
+        assert_eq!(args.api_token(false).unwrap().as_deref(), Some("ci-token"));
+        assert_eq!(args.api_token(true).unwrap(), None);
+
+        let args =
+            Args::try_parse_from(["pingvin-cli", "--token-env", TOKEN_ENV, "-f", "notes.txt"])
+                .unwrap();
+        assert_eq!(args.api_token(true).unwrap().as_deref(), Some("ci-token"));
+
+        std::env::remove_var(TOKEN_ENV);
+    }
+}
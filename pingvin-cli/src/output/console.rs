@@ -1,6 +1,8 @@
-use crate::api::{PublicConfiguration, UploadEvent, UploadEventCallback};
+use std::cell::RefCell;
 
-use super::AppOutput;
+use crate::api::{self, PublicConfiguration, RecipientDelivery, UploadEvent, UploadEventCallback};
+
+use super::{progress::ProgressRenderer, AppOutput};
 
 struct ConsoleAppOutput {}
 
@@ -15,23 +17,62 @@ impl AppOutput for ConsoleAppOutput {
         &self,
         server_config: &PublicConfiguration,
     ) -> anyhow::Result<Box<UploadEventCallback>> {
-        let app_url = server_config
-            .get_string("general.appUrl")
-            .unwrap_or("")
-            .to_string();
-
-        Ok(Box::new(move |event| match event {
-            UploadEvent::ShareCreated { share_id } => {
-                let share_url = format!("{}/s/{}", app_url, share_id);
-                log::info!("Share has been created: {}", share_url);
-            }
-            UploadEvent::ShareCompleted => {
-                log::info!("Upload completed");
+        let app_url = server_config.app_url().to_string();
+        let renderer = RefCell::new(ProgressRenderer::new());
+
+        Ok(Box::new(move |event| {
+            if let UploadEvent::UploadProgress(progress) = &event {
+                renderer.borrow_mut().update(progress);
+                return;
             }
-            UploadEvent::UploadError { file, error } => {
-                log::error!("Failed to upload {}: {}", file.display(), error);
+
+            /* remove the progress bars, so they do not get mixed up with the messages */
+            renderer.borrow_mut().clear();
+            match event {
+                UploadEvent::ShareCreated { share_id } => {
+                    let share_url = api::share_url(&app_url, &share_id);
+                    log::info!("Share has been created: {}", share_url);
+                }
+                UploadEvent::ShareReopened { share_id } => {
+                    let share_url = api::share_url(&app_url, &share_id);
+                    log::info!("Share has been reopened: {}", share_url);
+                }
+                UploadEvent::ShareCompleted => {
+                    log::info!("Upload completed");
+                }
+                UploadEvent::UploadError { file, error } => {
+                    log::error!("Failed to upload {}: {}", file.display(), error);
+                }
+                UploadEvent::UploadRetry {
+                    file,
+                    chunk_index,
+                    chunk_count,
+                    attempt,
+                    error,
+                } => {
+                    log::warn!(
+                        "Retrying chunk {}/{} of {} (attempt {}): {:#}",
+                        chunk_index + 1,
+                        chunk_count,
+                        file.display(),
+                        attempt,
+                        error
+                    );
+                }
+                UploadEvent::RecipientNotified {
+                    recipient,
+                    delivery,
+                } => match delivery {
+                    RecipientDelivery::Sent => log::info!("Share has been sent to {}", recipient),
+                    RecipientDelivery::Failed(error) => {
+                        log::error!("Failed to send share to {}: {:#}", recipient, error)
+                    }
+                    RecipientDelivery::Unconfirmed(error) => {
+                        log::warn!("Share may not have been sent to {}: {:#}", recipient, error)
+                    }
+                },
+                UploadEvent::UploadProgress(_) => {}
             }
-            UploadEvent::UploadProgress(_) => {}
         }))
     }
 }
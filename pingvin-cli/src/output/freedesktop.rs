@@ -0,0 +1,493 @@
+use std::{
+    collections::HashMap,
+    sync::{mpsc as std_mpsc, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use futures::StreamExt;
+use tokio::{sync::mpsc, time};
+use zbus::{proxy, zvariant::Value, Connection};
+
+use crate::{
+    api::{
+        self, PublicConfiguration, RecipientDelivery, UploadEvent, UploadEventCallback,
+        UploadProgress,
+    },
+    browser, clipboard,
+    shares::format_size,
+};
+
+use super::AppOutput;
+
+const APP_NAME: &str = "Pingvin Share";
+
+/// Minimum time between two updates of the progress notification
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time the user has to open the share from the completion notification
+const ACTION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Action of the completion notification which opens the share
+const OPEN_ACTION: &str = "open";
+
+#[proxy(
+    interface = "org.freedesktop.Notifications",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Notifications {
+    #[allow(clippy::too_many_arguments)]
+    fn notify(
+        &self,
+        app_name: &str,
+        replaces_id: u32,
+        app_icon: &str,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+        expire_timeout: i32,
+    ) -> zbus::Result<u32>;
+
+    #[zbus(signal)]
+    fn action_invoked(&self, id: u32, action_key: String) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn notification_closed(&self, id: u32, reason: u32) -> zbus::Result<()>;
+}
+
+/// Handles the share URL, e.g. by copying or opening it
+type UrlHandler = Box<dyn Fn(&str) -> anyhow::Result<()> + Send>;
+
+enum Message {
+    ShareCreated {
+        url: String,
+    },
+    Progress(UploadProgress),
+    Completed,
+    Error(String),
+
+    /// Stop processing messages and wait for the user to act on the completion notification
+    Finish,
+}
+
+/// Shows the upload within a single notification, which is replaced on every update
+struct Notifier<'a> {
+    proxy: NotificationsProxy<'a>,
+
+    /// Id of the current notification or zero if none has been shown yet
+    id: u32,
+    share_url: Option<String>,
+    last_progress: Option<Instant>,
+    copy_url: UrlHandler,
+}
+
+impl Notifier<'_> {
+    async fn notify(
+        &mut self,
+        summary: &str,
+        body: &str,
+        actions: &[&str],
+        hints: HashMap<&str, Value<'_>>,
+    ) -> anyhow::Result<()> {
+        self.id = self
+            .proxy
+            .notify(APP_NAME, self.id, "", summary, body, actions, hints, -1)
+            .await
+            .context("show notification")?;
+        Ok(())
+    }
+
+    async fn handle(&mut self, message: Message) -> anyhow::Result<()> {
+        match message {
+            Message::ShareCreated { url } => {
+                self.notify("Uploading files", &url, &[], progress_hints(0))
+                    .await?;
+                self.share_url = Some(url);
+            }
+            Message::Progress(progress) => {
+                let now = Instant::now();
+                if self
+                    .last_progress
+                    .is_some_and(|last| now.duration_since(last) < PROGRESS_INTERVAL)
+                {
+                    return Ok(());
+                }
+                self.last_progress = Some(now);
+
+                let percent = match progress.bytes_total {
+                    0 => 0,
+                    total => (progress.bytes_uploaded * 100 / total).min(100) as i32,
+                };
+                let body = format!(
+                    "{} of {} ({} of {} files)",
+                    format_size(progress.bytes_uploaded),
+                    format_size(progress.bytes_total),
+                    progress.files_uploaded,
+                    progress.files_total
+                );
+                self.notify("Uploading files", &body, &[], progress_hints(percent))
+                    .await?;
+            }
+            Message::Completed => {
+                let url = self.share_url.clone().unwrap_or_default();
+                let body = match (self.copy_url)(&url) {
+                    Ok(()) => format!("The share URL has been copied to your clipboard\n{}", url),
+                    Err(err) => {
+                        log::warn!("Failed to copy URL to clipboard: {:#}", err);
+                        url
+                    }
+                };
+                self.notify(
+                    "Upload completed",
+                    &body,
+                    &["default", "Open", OPEN_ACTION, "Open"],
+                    HashMap::new(),
+                )
+                .await?;
+            }
+            Message::Error(message) => {
+                /* critical urgency */
+                let hints = HashMap::from([("urgency", Value::U8(2))]);
+                self.notify("Upload failed", &message, &[], hints).await?;
+            }
+            Message::Finish => {}
+        }
+        Ok(())
+    }
+}
+
+fn progress_hints(percent: i32) -> HashMap<&'static str, Value<'static>> {
+    HashMap::from([("value", Value::I32(percent))])
+}
+
+async fn run(
+    connection: Connection,
+    mut receiver: mpsc::UnboundedReceiver<Message>,
+    copy_url: UrlHandler,
+    open_url: UrlHandler,
+) -> anyhow::Result<()> {
+    let proxy = NotificationsProxy::new(&connection).await?;
+
+    /* subscribe before showing notifications, so no signal gets lost */
+    let mut actions = proxy.receive_action_invoked().await?;
+    let mut closed = proxy.receive_notification_closed().await?;
+
+    let mut notifier = Notifier {
+        proxy,
+        id: 0,
+        share_url: None,
+        last_progress: None,
+        copy_url,
+    };
+    let mut completed = false;
+    while let Some(message) = receiver.recv().await {
+        match message {
+            Message::Finish => break,
+            Message::Completed => completed = true,
+            Message::Error(_) => completed = false,
+            _ => {}
+        }
+        notifier.handle(message).await?;
+    }
+
+    let (true, Some(url)) = (completed, notifier.share_url) else {
+        return Ok(());
+    };
+    let id = notifier.id;
+    let wait_for_action = async {
+        loop {
+            tokio::select! {
+                Some(signal) = actions.next() => {
+                    let args = signal.args()?;
+                    if *args.id() == id {
+                        if matches!(args.action_key().as_str(), "default" | OPEN_ACTION) {
+                            open_url(&url)?;
+                        }
+                        return Ok(());
+                    }
+                }
+                Some(signal) = closed.next() => {
+                    if *signal.args()?.id() == id {
+                        return Ok(());
+                    }
+                }
+                else => return Ok(()),
+            }
+        }
+    };
+
+    time::timeout(ACTION_TIMEOUT, wait_for_action)
+        .await
+        .unwrap_or(Ok(()))
+}
+
+struct DesktopAppOutput {
+    sender: mpsc::UnboundedSender<Message>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl AppOutput for DesktopAppOutput {
+    fn show_upload_error(&self, error: &anyhow::Error) {
+        for line in format!("{:#}", error).lines() {
+            log::error!("{:#}", line);
+        }
+        let _ = self.sender.send(Message::Error(format!("{:#}", error)));
+    }
+
+    fn create_upload_handler(
+        &self,
+        server_config: &PublicConfiguration,
+    ) -> anyhow::Result<Box<UploadEventCallback>> {
+        let app_url = server_config.app_url().to_string();
+        let sender = self.sender.clone();
+
+        Ok(Box::new(move |event| {
+            let message = match event {
+                UploadEvent::ShareCreated { share_id }
+                | UploadEvent::ShareReopened { share_id } => {
+                    let url = api::share_url(&app_url, &share_id);
+                    log::info!("Uploading to {}", url);
+                    Message::ShareCreated { url }
+                }
+                UploadEvent::UploadProgress(progress) => Message::Progress(progress),
+                UploadEvent::ShareCompleted => {
+                    log::info!("Upload completed");
+                    Message::Completed
+                }
+                UploadEvent::UploadError { file, error } => {
+                    log::error!("Failed to upload {}: {}", file.display(), error);
+                    return;
+                }
+                UploadEvent::UploadRetry { file, error, .. } => {
+                    log::warn!("Retrying upload of {}: {:#}", file.display(), error);
+                    return;
+                }
+                UploadEvent::RecipientNotified {
+                    recipient,
+                    delivery,
+                } => {
+                    match delivery {
+                        RecipientDelivery::Sent => {
+                            log::info!("Share has been sent to {}", recipient)
+                        }
+                        RecipientDelivery::Failed(error) => {
+                            log::error!("Failed to send share to {}: {:#}", recipient, error)
+                        }
+                        RecipientDelivery::Unconfirmed(error) => {
+                            log::warn!("Share may not have been sent to {}: {:#}", recipient, error)
+                        }
+                    }
+                    return;
+                }
+            };
+            let _ = sender.send(message);
+        }))
+    }
+
+    fn finish(&self) {
+        let _ = self.sender.send(Message::Finish);
+        if let Some(worker) = self.worker.lock().unwrap().take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+pub fn create() -> anyhow::Result<Box<dyn AppOutput>> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let (ready_sender, ready_receiver) = std_mpsc::channel();
+
+    /* the upload callbacks are synchronous, hence D-Bus is served by a separate runtime */
+    let worker = thread::spawn(move || {
+        let runtime = match tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+        {
+            Ok(runtime) => runtime,
+            Err(err) => {
+                let _ = ready_sender.send(Err(anyhow::Error::from(err)));
+                return;
+            }
+        };
+
+        runtime.block_on(async move {
+            let connection = match Connection::session().await {
+                Ok(connection) => connection,
+                Err(err) => {
+                    let _ = ready_sender.send(Err(anyhow::Error::from(err)));
+                    return;
+                }
+            };
+            let _ = ready_sender.send(Ok(()));
+
+            let copy_url = Box::new(|url: &str| clipboard::system()?.set_text(url));
+            let open_url = Box::new(browser::open);
+            if let Err(err) = run(connection, receiver, copy_url, open_url).await {
+                log::error!("Failed to show the desktop notification: {:#}", err);
+            }
+        });
+    });
+
+    ready_receiver
+        .recv()
+        .context("notification worker")?
+        .context("connect to the D-Bus session bus")?;
+    Ok(Box::new(DesktopAppOutput {
+        sender,
+        worker: Mutex::new(Some(worker)),
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use tokio::{net::UnixStream, sync::mpsc};
+    use zbus::{connection::Builder, interface, zvariant::OwnedValue, Guid, SignalContext};
+
+    use super::{run, Message, OPEN_ACTION};
+    use crate::api::UploadProgress;
+
+    #[derive(Debug, Clone)]
+    struct Notification {
+        replaces_id: u32,
+        summary: String,
+        actions: Vec<String>,
+        progress: Option<i32>,
+    }
+
+    /// Notification daemon which invokes the open action of every notification offering it
+    struct StubDaemon {
+        notifications: Arc<Mutex<Vec<Notification>>>,
+        next_id: u32,
+    }
+
+    #[interface(name = "org.freedesktop.Notifications")]
+    impl StubDaemon {
+        #[allow(clippy::too_many_arguments)]
+        async fn notify(
+            &mut self,
+            #[zbus(signal_context)] context: SignalContext<'_>,
+            _app_name: String,
+            replaces_id: u32,
+            _app_icon: String,
+            summary: String,
+            _body: String,
+            actions: Vec<String>,
+            hints: HashMap<String, OwnedValue>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            let id = match replaces_id {
+                0 => {
+                    self.next_id += 1;
+                    self.next_id
+                }
+                id => id,
+            };
+
+            let offers_open = actions.iter().any(|action| action == OPEN_ACTION);
+            self.notifications.lock().unwrap().push(Notification {
+                replaces_id,
+                summary,
+                actions,
+                progress: hints
+                    .get("value")
+                    .and_then(|value| i32::try_from(value).ok()),
+            });
+
+            if offers_open {
+                let _ = Self::action_invoked(&context, id, OPEN_ACTION).await;
+            }
+            id
+        }
+
+        #[zbus(signal)]
+        async fn action_invoked(
+            context: &SignalContext<'_>,
+            id: u32,
+            action_key: &str,
+        ) -> zbus::Result<()>;
+    }
+
+    #[tokio::test]
+    async fn test_notifications() {
+        let notifications = Arc::new(Mutex::new(vec![]));
+        let (client_stream, server_stream) = UnixStream::pair().unwrap();
+        let server = Builder::unix_stream(server_stream)
+            .server(Guid::generate())
+            .unwrap()
+            .p2p()
+            .serve_at(
+                "/org/freedesktop/Notifications",
+                StubDaemon {
+                    notifications: notifications.clone(),
+                    next_id: 0,
+                },
+            )
+            .unwrap()
+            .build();
+        let client = Builder::unix_stream(client_stream).p2p().build();
+        let (_server, client) = futures::try_join!(server, client).unwrap();
+
+        let copied = Arc::new(Mutex::new(vec![]));
+        let opened = Arc::new(Mutex::new(vec![]));
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let url = "http://pingvin.local/s/abc".to_string();
+        sender
+            .send(Message::ShareCreated { url: url.clone() })
+            .unwrap();
+        for bytes_uploaded in [50, 60] {
+            sender
+                .send(Message::Progress(UploadProgress {
+                    files_total: 1,
+                    bytes_total: 100,
+                    bytes_uploaded,
+                    ..Default::default()
+                }))
+                .unwrap();
+        }
+        sender.send(Message::Completed).unwrap();
+        sender.send(Message::Finish).unwrap();
+
+        run(
+            client,
+            receiver,
+            Box::new({
+                let copied = copied.clone();
+                move |url| {
+                    copied.lock().unwrap().push(url.to_string());
+                    Ok(())
+                }
+            }),
+            Box::new({
+                let opened = opened.clone();
+                move |url| {
+                    opened.lock().unwrap().push(url.to_string());
+                    Ok(())
+                }
+            }),
+        )
+        .await
+        .unwrap();
+
+        let notifications = notifications.lock().unwrap().clone();
+
+        /* the second progress update has been throttled */
+        assert_eq!(notifications.len(), 3);
+        assert_eq!(notifications[0].replaces_id, 0);
+        assert_eq!(notifications[0].progress, Some(0));
+        assert_eq!(notifications[1].replaces_id, 1);
+        assert_eq!(notifications[1].progress, Some(50));
+        assert_eq!(notifications[2].replaces_id, 1);
+        assert_eq!(notifications[2].summary, "Upload completed");
+        assert!(notifications[2].actions.contains(&OPEN_ACTION.to_string()));
+
+        assert_eq!(*copied.lock().unwrap(), vec![url.clone()]);
+        assert_eq!(*opened.lock().unwrap(), vec![url]);
+    }
+}
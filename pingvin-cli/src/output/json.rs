@@ -0,0 +1,320 @@
+//! Newline delimited JSON written to stdout, one object per line.
+//!
+//! Every object contains the schema `version` and the `event` type.
+//! The last object of an upload is the `summary`, which is also written if the upload failed.
+//! Fields will only be added within a schema version, changing or removing fields requires a new version.
+
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use serde::Serialize;
+
+use crate::api::{self, PublicConfiguration, RecipientDelivery, UploadEvent, UploadEventCallback};
+
+use super::AppOutput;
+
+/// Version of the JSON schema
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+struct Line<'a> {
+    version: u32,
+
+    #[serde(flatten)]
+    event: Event<'a>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    ShareCreated {
+        share_id: &'a str,
+        url: &'a str,
+    },
+    ShareReopened {
+        share_id: &'a str,
+        url: &'a str,
+    },
+    UploadProgress {
+        files_total: u64,
+        files_uploaded: u64,
+        files_failed: u64,
+        bytes_total: u64,
+        bytes_uploaded: u64,
+    },
+    UploadRetry {
+        file: String,
+        chunk_index: usize,
+        chunk_count: usize,
+        attempt: u32,
+        error: String,
+    },
+    UploadError {
+        file: String,
+        error: String,
+    },
+    RecipientNotified {
+        recipient: &'a str,
+
+        /// Either `sent`, `failed` or `unconfirmed`
+        delivery: &'static str,
+        error: Option<String>,
+    },
+    ShareCompleted,
+    Error {
+        message: String,
+    },
+    Summary(&'a Summary),
+}
+
+/// Outcome of the upload
+#[derive(Debug, Default, Serialize)]
+struct Summary {
+    share_id: Option<String>,
+    url: Option<String>,
+    completed: bool,
+    files_uploaded: u64,
+    files_failed: u64,
+    bytes_uploaded: u64,
+    bytes_total: u64,
+}
+
+struct JsonWriter {
+    writer: Box<dyn Write>,
+    summary: Summary,
+}
+
+impl JsonWriter {
+    fn write(&mut self, event: Event) {
+        let line = Line {
+            version: SCHEMA_VERSION,
+            event,
+        };
+        let result = serde_json::to_writer(&mut self.writer, &line)
+            .map_err(io::Error::from)
+            .and_then(|()| self.writer.write_all(b"\n"))
+            .and_then(|()| self.writer.flush());
+        if let Err(err) = result {
+            log::warn!("Failed to write the JSON output: {}", err);
+        }
+    }
+
+    /// Record the share within the summary and return its URL
+    fn share_started(&mut self, app_url: &str, share_id: &str) -> String {
+        let url = api::share_url(app_url, share_id);
+        self.summary.share_id = Some(share_id.to_string());
+        self.summary.url = Some(url.clone());
+        url
+    }
+
+    fn write_summary(&mut self) {
+        let summary = std::mem::take(&mut self.summary);
+        self.write(Event::Summary(&summary));
+        self.summary = summary;
+    }
+}
+
+struct JsonAppOutput {
+    writer: Rc<RefCell<JsonWriter>>,
+}
+
+impl AppOutput for JsonAppOutput {
+    fn show_upload_error(&self, error: &anyhow::Error) {
+        let mut writer = self.writer.borrow_mut();
+        writer.write(Event::Error {
+            message: format!("{:#}", error),
+        });
+        if writer.summary.share_id.is_some() && !writer.summary.completed {
+            writer.write_summary();
+        }
+    }
+
+    fn create_upload_handler(
+        &self,
+        server_config: &PublicConfiguration,
+    ) -> anyhow::Result<Box<UploadEventCallback>> {
+        let app_url = server_config.app_url().to_string();
+        let writer = self.writer.clone();
+
+        Ok(Box::new(move |event| {
+            let mut writer = writer.borrow_mut();
+            match event {
+                UploadEvent::ShareCreated { share_id } => {
+                    let url = writer.share_started(&app_url, &share_id);
+                    writer.write(Event::ShareCreated {
+                        share_id: &share_id,
+                        url: &url,
+                    });
+                }
+                UploadEvent::ShareReopened { share_id } => {
+                    let url = writer.share_started(&app_url, &share_id);
+                    writer.write(Event::ShareReopened {
+                        share_id: &share_id,
+                        url: &url,
+                    });
+                }
+                UploadEvent::ShareCompleted => {
+                    writer.summary.completed = true;
+                    writer.write(Event::ShareCompleted);
+                    writer.write_summary();
+                }
+                UploadEvent::UploadProgress(progress) => {
+                    writer.summary.files_uploaded = progress.files_uploaded;
+                    writer.summary.files_failed = progress.files_failed;
+                    writer.summary.bytes_uploaded = progress.bytes_uploaded;
+                    writer.summary.bytes_total = progress.bytes_total;
+                    writer.write(Event::UploadProgress {
+                        files_total: progress.files_total,
+                        files_uploaded: progress.files_uploaded,
+                        files_failed: progress.files_failed,
+                        bytes_total: progress.bytes_total,
+                        bytes_uploaded: progress.bytes_uploaded,
+                    });
+                }
+                UploadEvent::UploadError { file, error } => writer.write(Event::UploadError {
+                    file: file.display().to_string(),
+                    error: format!("{:#}", error),
+                }),
+                UploadEvent::UploadRetry {
+                    file,
+                    chunk_index,
+                    chunk_count,
+                    attempt,
+                    error,
+                } => writer.write(Event::UploadRetry {
+                    file: file.display().to_string(),
+                    chunk_index,
+                    chunk_count,
+                    attempt,
+                    error: format!("{:#}", error),
+                }),
+                UploadEvent::RecipientNotified {
+                    recipient,
+                    delivery,
+                } => {
+                    let (delivery, error) = match delivery {
+                        RecipientDelivery::Sent => ("sent", None),
+                        RecipientDelivery::Failed(error) => ("failed", Some(error)),
+                        RecipientDelivery::Unconfirmed(error) => ("unconfirmed", Some(error)),
+                    };
+                    writer.write(Event::RecipientNotified {
+                        recipient: &recipient,
+                        delivery,
+                        error: error.map(|error| format!("{:#}", error)),
+                    })
+                }
+            }
+        }))
+    }
+}
+
+fn create_with_writer(writer: Box<dyn Write>) -> Box<dyn AppOutput> {
+    Box::new(JsonAppOutput {
+        writer: Rc::new(RefCell::new(JsonWriter {
+            writer,
+            summary: Summary::default(),
+        })),
+    })
+}
+
+pub fn create() -> anyhow::Result<Box<dyn AppOutput>> {
+    Ok(create_with_writer(Box::new(io::stdout())))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        cell::RefCell,
+        io::{self, Write},
+        rc::Rc,
+        sync::Arc,
+    };
+
+    use serde_json::Value;
+
+    use super::create_with_writer;
+    use crate::api::{PublicConfiguration, UploadEvent, UploadProgress};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(data)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Buffer {
+        fn lines(&self) -> Vec<Value> {
+            String::from_utf8(self.0.borrow().clone())
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_json_output() {
+        let buffer = Buffer::default();
+        let output = create_with_writer(Box::new(buffer.clone()));
+        let handler = output
+            .create_upload_handler(&PublicConfiguration::new(vec![]))
+            .unwrap();
+
+        handler(UploadEvent::ShareCreated {
+            share_id: "abc".to_string(),
+        });
+        handler(UploadEvent::UploadProgress(UploadProgress {
+            files_total: 2,
+            files_uploaded: 1,
+            files_failed: 1,
+            bytes_total: 30,
+            bytes_uploaded: 10,
+            files_active: vec![],
+        }));
+        handler(UploadEvent::UploadError {
+            file: "b.txt".into(),
+            error: Arc::new(anyhow::anyhow!("rejected")),
+        });
+        handler(UploadEvent::ShareCompleted);
+
+        let lines = buffer.lines();
+        assert!(lines.iter().all(|line| line["version"] == 1));
+        let events = lines
+            .iter()
+            .map(|line| line["event"].as_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            [
+                "share_created",
+                "upload_progress",
+                "upload_error",
+                "share_completed",
+                "summary"
+            ]
+        );
+        assert_eq!(lines[0]["url"], lines[4]["url"]);
+        assert_eq!(lines[2]["file"], "b.txt");
+        assert_eq!(lines[4]["share_id"], "abc");
+        assert_eq!(lines[4]["completed"], true);
+        assert_eq!(lines[4]["files_uploaded"], 1);
+        assert_eq!(lines[4]["files_failed"], 1);
+        assert_eq!(lines[4]["bytes_uploaded"], 10);
+
+        /* errors after the upload completed do not repeat the summary */
+        output.show_upload_error(&anyhow::anyhow!("failed"));
+        let lines = buffer.lines();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[5]["event"], "error");
+        assert_eq!(lines[5]["message"], "failed");
+    }
+}
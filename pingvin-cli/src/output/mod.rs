@@ -1,17 +1,31 @@
-use crate::api::{PublicConfiguration, UploadEventCallback};
+use crate::api::{DownloadEvent, DownloadEventCallback, PublicConfiguration, UploadEventCallback};
 use clap::ValueEnum;
+use serde::Deserialize;
 
 mod console;
 
+#[cfg(target_os = "linux")]
+mod freedesktop;
+mod json;
+mod progress;
+pub use progress::clear_bars;
+
 #[cfg(target_family = "windows")]
 mod win;
 
-#[derive(ValueEnum, Clone, Debug, PartialEq, Copy)]
+#[derive(ValueEnum, Deserialize, Clone, Debug, PartialEq, Copy)]
 #[clap(rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
 pub enum OutputType {
     Console,
 
+    /// Newline delimited JSON events on stdout
+    Json,
+
     WindowsNotification,
+
+    /// Notifications of the freedesktop notification service on Linux desktops
+    DesktopNotification,
 }
 
 pub trait AppOutput {
@@ -20,11 +34,45 @@ pub trait AppOutput {
         &self,
         server_config: &PublicConfiguration,
     ) -> anyhow::Result<Box<UploadEventCallback>>;
+
+    /// Called once before the application exits, e.g. to wait for pending notifications
+    fn finish(&self) {}
+
+    fn create_download_handler(&self) -> anyhow::Result<Box<DownloadEventCallback>> {
+        Ok(Box::new(|event| match event {
+            DownloadEvent::FileDownloaded { path } => {
+                log::info!("Downloaded {}", path.display());
+            }
+            DownloadEvent::FileSkipped { path } => {
+                log::info!(
+                    "Skipping {} as it has already been downloaded",
+                    path.display()
+                );
+            }
+            DownloadEvent::DownloadError { file, error } => {
+                log::error!("Failed to download {}: {:#}", file, error);
+            }
+            DownloadEvent::DownloadCompleted { directory } => {
+                log::info!("Download to {} completed", directory.display());
+            }
+            DownloadEvent::DownloadProgress(progress) => {
+                log::trace!(
+                    "Downloading {} ({}/{} files, {}/{} bytes)",
+                    progress.file_name,
+                    progress.files_downloaded,
+                    progress.files_total,
+                    progress.bytes_downloaded,
+                    progress.bytes_total
+                );
+            }
+        }))
+    }
 }
 
 pub fn create(target: OutputType) -> anyhow::Result<Box<dyn AppOutput>> {
     match target {
         OutputType::Console => console::create(),
+        OutputType::Json => json::create(),
 
         OutputType::WindowsNotification => {
             #[cfg(target_family = "windows")]
@@ -33,5 +81,13 @@ pub fn create(target: OutputType) -> anyhow::Result<Box<dyn AppOutput>> {
             #[cfg(not(target_family = "windows"))]
             anyhow::bail!("output type is not supported on this platform");
         }
+
+        OutputType::DesktopNotification => {
+            #[cfg(target_os = "linux")]
+            return freedesktop::create();
+
+            #[cfg(not(target_os = "linux"))]
+            anyhow::bail!("output type is not supported on this platform");
+        }
     }
 }
@@ -0,0 +1,390 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    env,
+    io::{self, IsTerminal, Write},
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::{api::UploadProgress, shares::format_size};
+
+/// Interval of the plain text progress lines if stdout is no terminal
+const TEXT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Time span the throughput is averaged over
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(5);
+
+const BAR_WIDTH: usize = 24;
+
+/// Width of the terminal if it can not be queried
+const DEFAULT_WIDTH: usize = 80;
+
+/// Lines of the progress bars currently shown on the terminal.
+/// Shared with the logger, which removes the bars before writing a record.
+static LINES_SHOWN: Mutex<usize> = Mutex::new(0);
+
+/// Estimates the throughput using the uploaded bytes of the recent samples
+#[derive(Debug, Default)]
+struct Throughput {
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl Throughput {
+    fn add(&mut self, time: Instant, bytes: u64) {
+        self.samples.push_back((time, bytes));
+        while self
+            .samples
+            .front()
+            .is_some_and(|(sample_time, _)| time.duration_since(*sample_time) > THROUGHPUT_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Bytes per second or None if there are not enough samples yet
+    fn bytes_per_second(&self) -> Option<f64> {
+        let ((first_time, first_bytes), (last_time, last_bytes)) =
+            (self.samples.front()?, self.samples.back()?);
+        let elapsed = last_time.duration_since(*first_time).as_secs_f64();
+        if elapsed < 0.5 {
+            return None;
+        }
+        Some(last_bytes.saturating_sub(*first_bytes) as f64 / elapsed)
+    }
+}
+
+/// Renders the upload progress as progress bars or periodic plain text lines
+pub struct ProgressRenderer {
+    terminal: bool,
+    throughput: Throughput,
+
+    /// Throughput of the files currently being uploaded by their index
+    file_throughput: HashMap<usize, Throughput>,
+    last_text: Option<Instant>,
+}
+
+impl ProgressRenderer {
+    pub fn new() -> Self {
+        Self {
+            /* fall back to plain text lines if the terminal does not support escape sequences */
+            terminal: io::stdout().is_terminal() && enable_escape_sequences(),
+            throughput: Throughput::default(),
+            file_throughput: HashMap::new(),
+            last_text: None,
+        }
+    }
+
+    pub fn update(&mut self, progress: &UploadProgress) {
+        let now = Instant::now();
+        self.throughput.add(now, progress.bytes_uploaded);
+        let rate = self.throughput.bytes_per_second();
+
+        self.file_throughput.retain(|file_index, _| {
+            progress
+                .files_active
+                .iter()
+                .any(|file| file.file_index == *file_index)
+        });
+        for file in &progress.files_active {
+            self.file_throughput
+                .entry(file.file_index)
+                .or_default()
+                .add(now, file.bytes_uploaded);
+        }
+
+        if !self.terminal {
+            if self
+                .last_text
+                .is_none_or(|last_text| now.duration_since(last_text) >= TEXT_INTERVAL)
+            {
+                self.last_text = Some(now);
+                log::info!("{}", format_text(progress, rate));
+            }
+            return;
+        }
+
+        let width = terminal_width()
+            .or_else(|| env::var("COLUMNS").ok()?.parse().ok())
+            .unwrap_or(DEFAULT_WIDTH);
+        let lines = format_bars(progress, rate, |file_index| {
+            self.file_throughput
+                .get(&file_index)
+                .and_then(Throughput::bytes_per_second)
+        })
+        .into_iter()
+        .map(|line| truncate(&line, width.saturating_sub(1)))
+        .collect::<Vec<_>>();
+
+        let mut lines_shown = LINES_SHOWN.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut stdout = io::stdout().lock();
+        let _ = write!(stdout, "{}", clear_sequence(*lines_shown));
+        for line in &lines {
+            let _ = writeln!(stdout, "{}", line);
+        }
+        let _ = stdout.flush();
+        *lines_shown = lines.len();
+    }
+
+    /// Remove the progress bars, e.g. before showing another message.
+    /// They will be shown again with the next update.
+    pub fn clear(&mut self) {
+        clear_bars();
+    }
+}
+
+/// Remove the progress bars of any renderer from the terminal.
+/// Called by the logger, so records are never erased by the next update.
+pub fn clear_bars() {
+    let mut lines_shown = LINES_SHOWN.lock().unwrap_or_else(PoisonError::into_inner);
+    if *lines_shown == 0 {
+        return;
+    }
+
+    let mut stdout = io::stdout().lock();
+    let _ = write!(stdout, "{}", clear_sequence(*lines_shown));
+    let _ = stdout.flush();
+    *lines_shown = 0;
+}
+
+/// Columns of the terminal stdout is attached to
+#[cfg(target_family = "unix")]
+fn terminal_width() -> Option<usize> {
+    let mut size = libc::winsize {
+        ws_row: 0,
+        ws_col: 0,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    };
+    match unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } {
+        0 if size.ws_col > 0 => Some(size.ws_col as usize),
+        _ => None,
+    }
+}
+
+/// Columns of the console window stdout is attached to
+#[cfg(target_family = "windows")]
+fn terminal_width() -> Option<usize> {
+    use windows::Win32::System::Console::{
+        GetConsoleScreenBufferInfo, GetStdHandle, CONSOLE_SCREEN_BUFFER_INFO, STD_OUTPUT_HANDLE,
+    };
+
+    let mut info = CONSOLE_SCREEN_BUFFER_INFO::default();
+    unsafe {
+        let handle = GetStdHandle(STD_OUTPUT_HANDLE).ok()?;
+        GetConsoleScreenBufferInfo(handle, &mut info).ok()?;
+    }
+    let width = info.srWindow.Right - info.srWindow.Left + 1;
+    (width > 0).then_some(width as usize)
+}
+
+#[cfg(not(any(target_family = "unix", target_family = "windows")))]
+fn terminal_width() -> Option<usize> {
+    None
+}
+
+/// Let the console interpret the escape sequences used to redraw the progress bars.
+/// Returns false if the console does not support them.
+#[cfg(target_family = "windows")]
+fn enable_escape_sequences() -> bool {
+    use windows::Win32::System::Console::{
+        GetConsoleMode, GetStdHandle, SetConsoleMode, CONSOLE_MODE,
+        ENABLE_VIRTUAL_TERMINAL_PROCESSING, STD_OUTPUT_HANDLE,
+    };
+
+    unsafe {
+        let Ok(handle) = GetStdHandle(STD_OUTPUT_HANDLE) else {
+            return false;
+        };
+        let mut mode = CONSOLE_MODE::default();
+        GetConsoleMode(handle, &mut mode).is_ok()
+            && SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING).is_ok()
+    }
+}
+
+#[cfg(not(target_family = "windows"))]
+fn enable_escape_sequences() -> bool {
+    true
+}
+
+/// Move the cursor up the given amount of lines and erase everything below
+fn clear_sequence(lines: usize) -> String {
+    match lines {
+        0 => String::new(),
+        lines => format!("\x1b[{}A\x1b[J", lines),
+    }
+}
+
+fn format_bar(done: u64, total: u64) -> String {
+    let fraction = match total {
+        0 => 0.0,
+        total => (done as f64 / total as f64).min(1.0),
+    };
+    let filled = (fraction * BAR_WIDTH as f64).round() as usize;
+    format!(
+        "[{}{}] {:>3}%",
+        "#".repeat(filled),
+        "-".repeat(BAR_WIDTH - filled),
+        (fraction * 100.0) as u32
+    )
+}
+
+/// Format a duration as h:mm:ss or m:ss
+fn format_duration(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+/// Throughput and the estimated time to upload the remaining bytes
+fn format_rate(remaining: u64, rate: Option<f64>) -> String {
+    match rate {
+        Some(rate) if rate >= 1.0 => {
+            format!(
+                "{}/s ETA {}",
+                format_size(rate as u64),
+                format_duration((remaining as f64 / rate).ceil() as u64)
+            )
+        }
+        _ => "-/s ETA -".to_string(),
+    }
+}
+
+/// Lines of the progress bars of the share and of each file being uploaded.
+/// The throughput of the files is looked up by their index.
+fn format_bars(
+    progress: &UploadProgress,
+    rate: Option<f64>,
+    file_rate: impl Fn(usize) -> Option<f64>,
+) -> Vec<String> {
+    let mut lines = vec![format!(
+        "{} {}/{}  {} of {} files  {}",
+        format_bar(progress.bytes_uploaded, progress.bytes_total),
+        format_size(progress.bytes_uploaded),
+        format_size(progress.bytes_total),
+        progress.files_uploaded,
+        progress.files_total,
+        format_rate(
+            progress.bytes_total.saturating_sub(progress.bytes_uploaded),
+            rate
+        ),
+    )];
+
+    for file in &progress.files_active {
+        lines.push(format!(
+            "{} {}/{}  {}  {}",
+            format_bar(file.bytes_uploaded, file.length),
+            format_size(file.bytes_uploaded),
+            format_size(file.length),
+            format_rate(
+                file.length.saturating_sub(file.bytes_uploaded),
+                file_rate(file.file_index)
+            ),
+            file.file
+                .file_name()
+                .unwrap_or(file.file.as_os_str())
+                .to_string_lossy(),
+        ));
+    }
+    lines
+}
+
+fn format_text(progress: &UploadProgress, rate: Option<f64>) -> String {
+    let percent = match progress.bytes_total {
+        0 => 0,
+        total => (progress.bytes_uploaded as f64 / total as f64 * 100.0).min(100.0) as u32,
+    };
+    format!(
+        "Uploaded {} of {} ({}%, {} of {} files, {})",
+        format_size(progress.bytes_uploaded),
+        format_size(progress.bytes_total),
+        percent,
+        progress.files_uploaded,
+        progress.files_total,
+        format_rate(
+            progress.bytes_total.saturating_sub(progress.bytes_uploaded),
+            rate
+        ),
+    )
+}
+
+fn truncate(line: &str, width: usize) -> String {
+    line.chars().take(width).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use super::{format_bars, format_duration, format_text, Throughput};
+    use crate::api::{FileProgress, UploadProgress};
+
+    #[test]
+    fn test_throughput() {
+        let start = Instant::now();
+        let mut throughput = Throughput::default();
+        throughput.add(start, 0);
+        assert_eq!(throughput.bytes_per_second(), None);
+
+        throughput.add(start + Duration::from_secs(2), 2000);
+        assert_eq!(throughput.bytes_per_second(), Some(1000.0));
+
+        /* old samples leave the window */
+        throughput.add(start + Duration::from_secs(10), 4000);
+        throughput.add(start + Duration::from_secs(11), 4500);
+        assert_eq!(throughput.bytes_per_second(), Some(500.0));
+    }
+
+    #[test]
+    fn test_format_progress() {
+        assert_eq!(format_duration(59), "0:59");
+        assert_eq!(format_duration(3 * 3600 + 61), "3:01:01");
+
+        let progress = UploadProgress {
+            files_total: 3,
+            files_uploaded: 1,
+            files_failed: 0,
+            bytes_total: 4096,
+            bytes_uploaded: 1024,
+            files_active: vec![
+                FileProgress {
+                    file_index: 1,
+                    file: "photos/beach.jpg".into(),
+                    length: 2048,
+                    bytes_uploaded: 1024,
+                },
+                FileProgress {
+                    file_index: 2,
+                    file: "photos/sunset.jpg".into(),
+                    length: 1024,
+                    bytes_uploaded: 0,
+                },
+            ],
+        };
+
+        let lines = format_bars(&progress, Some(512.0), |file_index| match file_index {
+            1 => Some(256.0),
+            _ => None,
+        });
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            "[######------------------]  25% 1.0 KiB/4.0 KiB  1 of 3 files  512 B/s ETA 0:06"
+        );
+        assert_eq!(
+            lines[1],
+            "[############------------]  50% 1.0 KiB/2.0 KiB  256 B/s ETA 0:04  beach.jpg"
+        );
+        assert_eq!(
+            lines[2],
+            "[------------------------]   0% 0 B/1.0 KiB  -/s ETA -  sunset.jpg"
+        );
+
+        assert_eq!(
+            format_text(&progress, None),
+            "Uploaded 1.0 KiB of 4.0 KiB (25%, 1 of 3 files, -/s ETA -)"
+        );
+    }
+}
@@ -13,7 +13,10 @@ use windows::{
 };
 use windows_core::HSTRING;
 
-use crate::api::{PublicConfiguration, UploadEvent, UploadEventCallback};
+use crate::{
+    api::{self, PublicConfiguration, RecipientDelivery, UploadEvent, UploadEventCallback},
+    clipboard,
+};
 
 use super::AppOutput;
 
@@ -173,10 +176,7 @@ impl AppOutput for WinNotificationAppOutput {
         &self,
         server_config: &PublicConfiguration,
     ) -> anyhow::Result<Box<UploadEventCallback>> {
-        let app_url = server_config
-            .get_string("general.appUrl")
-            .unwrap_or("")
-            .to_string();
+        let app_url = server_config.app_url().to_string();
 
         let notifier = Arc::new(
             Err(())
@@ -205,10 +205,15 @@ impl AppOutput for WinNotificationAppOutput {
             move |event| -> anyhow::Result<()> {
                 match event {
                     UploadEvent::ShareCreated { share_id } => {
-                        share_url = Some(format!("{}/s/{}", app_url, share_id));
+                        share_url = Some(api::share_url(&app_url, &share_id));
                         log::info!("Share has been created: {}", share_url.as_ref().unwrap());
                         progress_notification.set_status("Uploading...")?;
                     }
+                    UploadEvent::ShareReopened { share_id } => {
+                        share_url = Some(api::share_url(&app_url, &share_id));
+                        log::info!("Share has been reopened: {}", share_url.as_ref().unwrap());
+                        progress_notification.set_status("Uploading...")?;
+                    }
                     UploadEvent::ShareCompleted => {
                         log::info!("Upload completed");
                         progress_notification.set_progress(1.0, "")?;
@@ -221,8 +226,10 @@ impl AppOutput for WinNotificationAppOutput {
                             &progress_notification.notification.Tag()?.to_string_lossy(),
                             share_url,
                         )?;
-                        if let Err(err) = clipboard_win::set_clipboard_string(&share_url) {
-                            log::warn!("Failed to copy URL to clipboard: {}", err);
+                        if let Err(err) =
+                            clipboard::system().and_then(|clipboard| clipboard.set_text(share_url))
+                        {
+                            log::warn!("Failed to copy URL to clipboard: {:#}", err);
                         } else {
                             log::info!("URL copied to clipboard");
                         }
@@ -230,21 +237,62 @@ impl AppOutput for WinNotificationAppOutput {
                     UploadEvent::UploadError { file, error } => {
                         log::error!("Failed to upload {}: {}", file.display(), error);
                     }
+                    UploadEvent::RecipientNotified {
+                        recipient,
+                        delivery,
+                    } => match delivery {
+                        RecipientDelivery::Sent => {
+                            log::info!("Share has been sent to {}", recipient);
+                        }
+                        RecipientDelivery::Failed(error) => {
+                            log::error!("Failed to send share to {}: {:#}", recipient, error);
+                            progress_notification
+                                .set_status(&format!("Failed to send share to {}", recipient))?;
+                        }
+                        RecipientDelivery::Unconfirmed(error) => {
+                            log::warn!(
+                                "Share may not have been sent to {}: {:#}",
+                                recipient,
+                                error
+                            );
+                            progress_notification.set_status(&format!(
+                                "Share may not have been sent to {}",
+                                recipient
+                            ))?;
+                        }
+                    },
+                    UploadEvent::UploadRetry {
+                        file,
+                        chunk_index,
+                        chunk_count,
+                        attempt,
+                        error,
+                    } => {
+                        log::warn!(
+                            "Retrying chunk {}/{} of {} (attempt {}): {:#}",
+                            chunk_index + 1,
+                            chunk_count,
+                            file.display(),
+                            attempt,
+                            error
+                        );
+                        progress_notification.set_status(&format!(
+                            "Retrying chunk {}/{} (attempt {})",
+                            chunk_index + 1,
+                            chunk_count,
+                            attempt
+                        ))?;
+                    }
                     UploadEvent::UploadProgress(progress) => {
                         let files_done = progress.files_failed + progress.files_uploaded;
-                        let file_done =
-                            progress.file_bytes_uploaded as f32 / progress.file_length as f32;
 
                         progress_notification.set_status(&format!(
                             "Uploading ({}/{})",
                             files_done, progress.files_total,
                         ))?;
                         progress_notification.set_progress(
-                            (files_done as f32 + file_done) / progress.files_total as f32,
-                            &format!(
-                                "{}/{} bytes",
-                                progress.file_bytes_uploaded, progress.file_length
-                            ),
+                            progress.bytes_uploaded as f32 / progress.bytes_total.max(1) as f32,
+                            &format!("{}/{} bytes", progress.bytes_uploaded, progress.bytes_total),
                         )?;
                     }
                 }
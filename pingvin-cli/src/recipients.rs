@@ -0,0 +1,95 @@
+use std::{fs, path::Path};
+
+use anyhow::Context;
+
+/// Basic sanity check of an email address.
+/// The server performs the actual validation, this only catches obvious typos early.
+pub fn validate_recipient(address: &str) -> anyhow::Result<()> {
+    let valid = match address.split_once('@') {
+        Some((local, domain)) => {
+            !local.is_empty()
+                && !domain.contains('@')
+                && domain.contains('.')
+                && !domain.starts_with('.')
+                && !domain.ends_with('.')
+                && !address.chars().any(char::is_whitespace)
+        }
+        None => false,
+    };
+
+    if !valid {
+        anyhow::bail!("invalid recipient email address '{}'", address);
+    }
+    Ok(())
+}
+
+/// Parse a recipients file containing one address per line.
+/// Empty lines and lines starting with '#' are ignored.
+pub fn parse_recipients(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Collect and validate the recipients given on the command line and within the recipients file
+pub fn collect_recipients(
+    recipients: &[String],
+    recipients_file: Option<&Path>,
+) -> anyhow::Result<Vec<String>> {
+    let mut result = recipients.to_vec();
+    if let Some(path) = recipients_file {
+        let content =
+            fs::read_to_string(path).with_context(|| format!("read {}", path.display()))?;
+        result.extend(parse_recipients(&content));
+    }
+
+    let mut unique = Vec::<String>::with_capacity(result.len());
+    for recipient in result {
+        validate_recipient(&recipient)?;
+        if !unique
+            .iter()
+            .any(|entry| entry.eq_ignore_ascii_case(&recipient))
+        {
+            unique.push(recipient);
+        }
+    }
+
+    Ok(unique)
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use super::{collect_recipients, validate_recipient};
+
+    #[test]
+    fn test_validate_recipient() {
+        assert!(validate_recipient("alice@example.com").is_ok());
+        assert!(validate_recipient("alice.smith+share@mail.example.org").is_ok());
+
+        assert!(validate_recipient("alice").is_err());
+        assert!(validate_recipient("@example.com").is_err());
+        assert!(validate_recipient("alice@localhost").is_err());
+        assert!(validate_recipient("alice@@example.com").is_err());
+        assert!(validate_recipient("alice @example.com").is_err());
+        assert!(validate_recipient("alice@example.").is_err());
+    }
+
+    #[test]
+    fn test_collect_recipients() {
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("recipients.txt");
+        fs::write(&path, "# team\nbob@example.com\n\n  Alice@Example.com \n").unwrap();
+
+        let recipients =
+            collect_recipients(&["alice@example.com".to_string()], Some(&path)).unwrap();
+        assert_eq!(recipients, vec!["alice@example.com", "bob@example.com"]);
+
+        fs::write(&path, "not an address\n").unwrap();
+        assert!(collect_recipients(&[], Some(&path)).is_err());
+    }
+}
@@ -0,0 +1,182 @@
+use anyhow::Context;
+use chrono::Local;
+use clap::Subcommand;
+
+use crate::{
+    api::{ExpireDuration, PingvinApi, PublicConfiguration, ReverseShareOptions},
+    shares::{format_size, format_table},
+};
+
+#[derive(Debug, Subcommand)]
+pub enum ReverseSharesAction {
+    /// Create a link others can use to upload shares into the account
+    Create(CreateArgs),
+
+    /// List the reverse shares of the signed in user
+    List,
+
+    /// Delete reverse shares of the signed in user
+    Delete {
+        /// Ids of the reverse shares to delete
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+}
+
+#[derive(Debug, clap::Args)]
+pub struct CreateArgs {
+    /// Maximum size of each uploaded share (e.g. 500MB, 2GiB).
+    /// Default: the maximum share size of the server
+    #[arg(long, value_parser = parse_byte_size, verbatim_doc_comment)]
+    pub max_size: Option<u64>,
+
+    /// How many shares can be uploaded using the link
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_uses: u32,
+
+    /// Expiration of the uploaded shares.
+    /// Format: <amount>-<unit>
+    /// Default: '1-day'
+    #[arg(long, verbatim_doc_comment)]
+    pub expire: Option<ExpireDuration>,
+
+    /// Send an email to the signed in user whenever a share has been uploaded
+    #[arg(long)]
+    pub notify: bool,
+}
+
+/// Parse a size given in bytes or with a decimal (KB, MB, ...) or binary (KiB, MiB, ...) unit
+fn parse_byte_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount = amount
+        .parse::<f64>()
+        .map_err(|_| format!("invalid size '{}'", value))?;
+
+    let factor: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "kb" => 1000,
+        "mb" => 1000_u64.pow(2),
+        "gb" => 1000_u64.pow(3),
+        "tb" => 1000_u64.pow(4),
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        unit => return Err(format!("invalid unit '{}'", unit)),
+    };
+    Ok((amount * factor as f64) as u64)
+}
+
+pub async fn create(
+    server_api: &PingvinApi,
+    server_config: &PublicConfiguration,
+    args: &CreateArgs,
+) -> anyhow::Result<()> {
+    let max_share_size = match args.max_size {
+        Some(size) => size,
+        None => server_config
+            .get_number("share.maxSize")
+            .and_then(|size| size.as_u64())
+            .context("missing max share size (--max-size)")?,
+    };
+    let share_expiration = args
+        .expire
+        .unwrap_or(ExpireDuration::Days(1))
+        .limit(server_config.max_expiration(), false)?;
+
+    let reverse_share = server_api
+        .create_reverse_share(&ReverseShareOptions {
+            max_share_size,
+            share_expiration,
+            max_use_count: args.max_uses,
+            send_email_notification: args.notify,
+            simplified: false,
+            public_access: true,
+        })
+        .await?;
+
+    log::info!(
+        "Created a reverse share for {} share(s) of up to {}",
+        args.max_uses,
+        format_size(max_share_size)
+    );
+    println!("{}", reverse_share.link);
+    Ok(())
+}
+
+pub async fn list(server_api: &PingvinApi) -> anyhow::Result<()> {
+    let reverse_shares = server_api.list_reverse_shares().await?;
+    if reverse_shares.is_empty() {
+        log::info!("There are no reverse shares.");
+        return Ok(());
+    }
+
+    let rows = reverse_shares
+        .iter()
+        .map(|reverse_share| {
+            vec![
+                reverse_share.id.clone(),
+                reverse_share.token.clone(),
+                format_size(reverse_share.max_share_size),
+                reverse_share
+                    .share_expiration
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+                reverse_share.remaining_uses.to_string(),
+                reverse_share
+                    .shares
+                    .iter()
+                    .map(|share| share.id.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    print!(
+        "{}",
+        format_table(
+            &["ID", "TOKEN", "MAX SIZE", "EXPIRES", "REMAINING", "SHARES"],
+            &rows
+        )
+    );
+    Ok(())
+}
+
+pub async fn delete(server_api: &PingvinApi, ids: &[String]) -> anyhow::Result<()> {
+    let mut failed = 0;
+    for id in ids {
+        match server_api.delete_reverse_share(id).await {
+            Ok(()) => log::info!("Deleted reverse share {}", id),
+            Err(err) => {
+                log::error!("Failed to delete reverse share {}: {:#}", id, err);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} reverse share(s) failed to delete", failed);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_byte_size;
+
+    #[test]
+    fn test_parse_byte_size() {
+        assert_eq!(parse_byte_size("1024"), Ok(1024));
+        assert_eq!(parse_byte_size("500MB"), Ok(500_000_000));
+        assert_eq!(parse_byte_size("1.5 KiB"), Ok(1536));
+        assert_eq!(parse_byte_size("2gib"), Ok(2 << 30));
+        assert!(parse_byte_size("10 apples").is_err());
+        assert!(parse_byte_size("MB").is_err());
+    }
+}
@@ -0,0 +1,563 @@
+use std::path::PathBuf;
+
+use anyhow::Context;
+use chrono::{DateTime, Local, Utc};
+use clap::{ArgGroup, Subcommand};
+
+use crate::{
+    api::{self, ExpireDuration, MyShare, PingvinApi, PublicConfiguration, ShareFile, UploadFile},
+    files,
+    output::AppOutput,
+    Args, FileSelectionArgs,
+};
+
+#[derive(Debug, Subcommand)]
+pub enum SharesAction {
+    /// List the shares of the signed in user.
+    ///
+    /// Expired shares are not listed and there is no filter for them (e.g. --expired),
+    /// as the server omits them from the shares of the user.
+    List(ListArgs),
+
+    /// Delete shares of the signed in user.
+    ///
+    /// Deleting expired shares (--expired) is not supported,
+    /// as the server omits them from the shares of the user and cleans them up by itself.
+    Delete(DeleteArgs),
+
+    /// Upload additional files to an existing share
+    Add(AddArgs),
+
+    /// Remove a file from a share
+    RmFile(RmFileArgs),
+
+    /// Replace a file of a share with a local file while keeping the share link
+    Replace(ReplaceArgs),
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ListArgs {
+    /// Only list shares whose name or id contains the given text (case insensitive)
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Only list shares expiring within the given duration.
+    /// Format: <amount>-<unit> (e.g. 7-days)
+    #[arg(long, verbatim_doc_comment)]
+    pub expires_within: Option<ExpireDuration>,
+}
+
+impl ListArgs {
+    fn matches(&self, share: &MyShare, now: DateTime<Utc>) -> bool {
+        if let Some(name) = &self.name {
+            let name = name.to_lowercase();
+            let share_name = share.name.as_deref().unwrap_or_default().to_lowercase();
+            if !share_name.contains(&name) && !share.id.to_lowercase().contains(&name) {
+                return false;
+            }
+        }
+
+        if let Some(limit) = self.expires_within.and_then(|within| within.after(now)) {
+            return share
+                .expires_at()
+                .is_some_and(|expiration| expiration > now && expiration <= limit);
+        }
+
+        true
+    }
+}
+
+#[derive(Debug, clap::Args)]
+#[command(group(ArgGroup::new("selection").required(true).multiple(true)))]
+pub struct DeleteArgs {
+    /// Ids of the shares to delete
+    #[arg(group = "selection")]
+    pub ids: Vec<String>,
+
+    /// Delete all shares created longer ago than the given duration.
+    /// Format: <amount>-<unit> (e.g. 30-days)
+    #[arg(long, group = "selection", value_parser = parse_age, verbatim_doc_comment)]
+    pub older_than: Option<ExpireDuration>,
+
+    /// Only list the shares which would be deleted
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// Parse the age of shares to delete, which must be a positive duration
+fn parse_age(value: &str) -> Result<ExpireDuration, String> {
+    let age = value
+        .parse::<ExpireDuration>()
+        .map_err(|err| err.to_string())?;
+    let now = Utc::now();
+    match age.before(now) {
+        Some(start) if start < now => Ok(age),
+        _ => Err("the age must be a positive duration".to_string()),
+    }
+}
+
+impl DeleteArgs {
+    /// Whether the share is selected by the filters.
+    /// Shares selected by their id are not affected by the filters.
+    fn matches(&self, share: &MyShare, now: DateTime<Utc>) -> bool {
+        let Some(created_before) = self.older_than.and_then(|age| age.before(now)) else {
+            return false;
+        };
+        if created_before >= now {
+            return false;
+        }
+
+        share
+            .created_at
+            .is_some_and(|created_at| created_at < created_before)
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub struct AddArgs {
+    /// Id of the share the files will be added to
+    pub id: String,
+
+    /// Files or directories to upload
+    #[arg(short, long = "file", required = true, num_args = 1..)]
+    pub files: Vec<PathBuf>,
+
+    #[command(flatten)]
+    pub file_selection: FileSelectionArgs,
+
+    /// Do not record the upload progress within the upload journal
+    #[arg(long)]
+    pub no_journal: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct RmFileArgs {
+    /// Id of the share
+    pub share: String,
+
+    /// Name or id of the file to remove
+    pub file: String,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ReplaceArgs {
+    /// Id of the share
+    pub share: String,
+
+    /// File which will be uploaded in place of the file with the same name
+    pub file: PathBuf,
+
+    /// Name of the file within the share to replace.
+    /// Defaults to the name of the local file.
+    #[arg(long)]
+    pub name: Option<String>,
+
+    /// Do not record the upload progress within the upload journal
+    #[arg(long)]
+    pub no_journal: bool,
+}
+
+/// Find a share of the signed in user by its id
+async fn find_share(server_api: &PingvinApi, share_id: &str) -> anyhow::Result<MyShare> {
+    server_api
+        .list_shares()
+        .await?
+        .into_iter()
+        .find(|share| share.id == share_id)
+        .with_context(|| format!("There is no share {} owned by the signed in user", share_id))
+}
+
+/// Find a file of the share by its id or its name
+fn find_file<'a>(share: &'a MyShare, name_or_id: &str) -> anyhow::Result<&'a ShareFile> {
+    if let Some(file) = share.files.iter().find(|file| file.id == name_or_id) {
+        return Ok(file);
+    }
+
+    let files = share
+        .files
+        .iter()
+        .filter(|file| file.name == name_or_id)
+        .collect::<Vec<_>>();
+    match files.as_slice() {
+        [file] => Ok(file),
+        [] => anyhow::bail!("The share {} contains no file {}", share.id, name_or_id),
+        files => anyhow::bail!(
+            "The share {} contains multiple files named {}, select one by its id: {}",
+            share.id,
+            name_or_id,
+            files
+                .iter()
+                .map(|file| file.id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+pub async fn list(
+    server_api: &PingvinApi,
+    server_config: &PublicConfiguration,
+    args: &ListArgs,
+) -> anyhow::Result<()> {
+    let now = Utc::now();
+    let shares = server_api
+        .list_shares()
+        .await?
+        .into_iter()
+        .filter(|share| args.matches(share, now))
+        .collect::<Vec<_>>();
+
+    if shares.is_empty() {
+        log::info!("There are no matching shares.");
+        return Ok(());
+    }
+
+    print_shares(&shares, server_config, now);
+    Ok(())
+}
+
+pub async fn delete(
+    server_api: &PingvinApi,
+    server_config: &PublicConfiguration,
+    args: &DeleteArgs,
+) -> anyhow::Result<()> {
+    let now = Utc::now();
+    let shares = server_api.list_shares().await?;
+
+    for id in &args.ids {
+        if !shares.iter().any(|share| &share.id == id) {
+            anyhow::bail!("There is no share {} owned by the signed in user", id);
+        }
+    }
+
+    let shares = shares
+        .into_iter()
+        .filter(|share| args.ids.contains(&share.id) || args.matches(share, now))
+        .collect::<Vec<_>>();
+
+    if shares.is_empty() {
+        log::info!("There are no matching shares.");
+        return Ok(());
+    }
+
+    if args.dry_run {
+        log::info!("The following {} share(s) would be deleted:", shares.len());
+        print_shares(&shares, server_config, now);
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    for share in &shares {
+        match server_api.delete_share(&share.id).await {
+            Ok(()) => log::info!("Deleted share {}", share.id),
+            Err(err) => {
+                log::error!("Failed to delete share {}: {:#}", share.id, err);
+                failed += 1;
+            }
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!("{} share(s) failed to delete", failed);
+    }
+    Ok(())
+}
+
+pub async fn add(
+    server_api: &PingvinApi,
+    server_config: &PublicConfiguration,
+    args: &Args,
+    add_args: &AddArgs,
+    output: &dyn AppOutput,
+) -> anyhow::Result<()> {
+    let files = files::collect_files(&add_args.files, &add_args.file_selection.collect_options())?;
+    if files.is_empty() {
+        anyhow::bail!("There are no files to upload");
+    }
+
+    let mut share_builder = server_api.append_to_share(add_args.id.clone());
+    for file in files {
+        share_builder.add_upload_file(file);
+    }
+    if !add_args.no_journal {
+        share_builder.set_journal_directory(api::journal_directory()?);
+    }
+    share_builder.set_retry_policy(args.retry_policy());
+    share_builder.set_parallel_uploads(args.parallel as usize);
+    share_builder.with_callback(output.create_upload_handler(server_config)?);
+    share_builder.upload().await?;
+    Ok(())
+}
+
+pub async fn remove_file(server_api: &PingvinApi, args: &RmFileArgs) -> anyhow::Result<()> {
+    let share = find_share(server_api, &args.share).await?;
+    let file = find_file(&share, &args.file)?;
+    if share.files.len() == 1 {
+        anyhow::bail!(
+            "{} is the last file of share {}, delete the share instead",
+            file.name,
+            share.id
+        );
+    }
+
+    /* the share needs to be reopened to remove files, which is done by the upload */
+    let mut share_builder = server_api.append_to_share(share.id.clone());
+    share_builder.remove_file(file.id.clone());
+    share_builder.upload().await?;
+
+    log::info!("Removed {} from share {}", file.name, share.id);
+    Ok(())
+}
+
+pub async fn replace(
+    server_api: &PingvinApi,
+    server_config: &PublicConfiguration,
+    args: &Args,
+    replace_args: &ReplaceArgs,
+    output: &dyn AppOutput,
+) -> anyhow::Result<()> {
+    let name = match &replace_args.name {
+        Some(name) => name.clone(),
+        None => replace_args
+            .file
+            .file_name()
+            .with_context(|| format!("expected a file name for {}", replace_args.file.display()))?
+            .to_string_lossy()
+            .to_string(),
+    };
+    if !replace_args.file.is_file() {
+        anyhow::bail!("{} is not a file", replace_args.file.display());
+    }
+
+    let share = find_share(server_api, &replace_args.share).await?;
+    let file = find_file(&share, &name)?;
+
+    let mut share_builder = server_api.append_to_share(share.id.clone());
+    share_builder.remove_file(file.id.clone());
+    share_builder.add_upload_file(UploadFile {
+        path: replace_args.file.clone(),
+        name,
+    });
+    if !replace_args.no_journal {
+        share_builder.set_journal_directory(api::journal_directory()?);
+    }
+    share_builder.set_retry_policy(args.retry_policy());
+    share_builder.with_callback(output.create_upload_handler(server_config)?);
+    share_builder.upload().await?;
+    Ok(())
+}
+
+fn print_shares(shares: &[MyShare], server_config: &PublicConfiguration, now: DateTime<Utc>) {
+    let rows = shares
+        .iter()
+        .map(|share| {
+            vec![
+                share.id.clone(),
+                share.name.clone().unwrap_or_default(),
+                api::share_url(server_config.app_url(), &share.id),
+                format_expiration(share, now),
+                share.views.to_string(),
+                format_size(share.total_size()),
+                share.files.len().to_string(),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    print!(
+        "{}",
+        format_table(
+            &["ID", "NAME", "URL", "EXPIRES", "VIEWS", "SIZE", "FILES"],
+            &rows
+        )
+    );
+}
+
+fn format_expiration(share: &MyShare, now: DateTime<Utc>) -> String {
+    let Some(expiration) = share.expires_at() else {
+        return "never".to_string();
+    };
+
+    let formatted = expiration
+        .with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string();
+    if share.is_expired(now) {
+        format!("{} (expired)", formatted)
+    } else {
+        formatted
+    }
+}
+
+/// Human readable size using binary units
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Align the rows into columns below the given headers
+pub fn format_table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut widths = headers
+        .iter()
+        .map(|header| header.chars().count())
+        .collect::<Vec<_>>();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let headers = headers
+        .iter()
+        .map(|header| header.to_string())
+        .collect::<Vec<_>>();
+
+    let mut result = String::new();
+    for row in std::iter::once(&headers).chain(rows) {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        result.push_str(line.trim_end());
+        result.push('\n');
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, TimeDelta, Utc};
+
+    use super::{
+        find_file, format_size, format_table, parse_age, DeleteArgs, ExpireDuration, ListArgs,
+        MyShare, ShareFile,
+    };
+
+    fn share(id: &str, name: Option<&str>, expiration: DateTime<Utc>) -> MyShare {
+        MyShare {
+            id: id.to_string(),
+            name: name.map(str::to_string),
+            expiration,
+            created_at: Some(expiration - TimeDelta::days(7)),
+            views: 0,
+            size: 0,
+            files: vec![],
+        }
+    }
+
+    #[test]
+    fn test_list_filter() {
+        let now = Utc::now();
+        let never = share("never", Some("Holiday Photos"), DateTime::UNIX_EPOCH);
+        let soon = share("soon", None, now + TimeDelta::hours(2));
+        let expired = share("old", Some("Invoices"), now - TimeDelta::days(1));
+
+        let args = ListArgs {
+            name: None,
+            expires_within: None,
+        };
+        assert!([&never, &soon, &expired]
+            .iter()
+            .all(|share| args.matches(share, now)));
+
+        let args = ListArgs {
+            name: Some("photos".to_string()),
+            ..args
+        };
+        assert!(args.matches(&never, now));
+        assert!(!args.matches(&expired, now));
+
+        let args = ListArgs {
+            name: None,
+            expires_within: Some("1-day".parse().unwrap()),
+        };
+        assert!(args.matches(&soon, now));
+        assert!(!args.matches(&never, now));
+        assert!(!args.matches(&expired, now));
+    }
+
+    #[test]
+    fn test_delete_filter() {
+        let now = Utc::now();
+        let expired = share("old", None, now - TimeDelta::days(1));
+        let recent = share("recent", None, now + TimeDelta::days(6));
+        let never = share("never", None, DateTime::UNIX_EPOCH);
+
+        let args = DeleteArgs {
+            ids: vec!["recent".to_string()],
+            older_than: None,
+            dry_run: false,
+        };
+        assert!(!args.matches(&recent, now));
+        assert!(!args.matches(&expired, now));
+
+        let args = DeleteArgs {
+            older_than: Some("3-days".parse().unwrap()),
+            ..args
+        };
+        assert!(args.matches(&expired, now));
+        assert!(!args.matches(&recent, now));
+
+        /* an age without duration must never select all shares */
+        assert!(parse_age("never").is_err());
+        assert!(parse_age("0-days").is_err());
+        assert!(parse_age("2-weeks").is_ok());
+        for older_than in [ExpireDuration::Never, ExpireDuration::Days(0)] {
+            let args = DeleteArgs {
+                ids: vec![],
+                older_than: Some(older_than),
+                dry_run: false,
+            };
+            assert!(!args.matches(&expired, now));
+            assert!(!args.matches(&never, now));
+        }
+    }
+
+    #[test]
+    fn test_find_file() {
+        let file = |id: &str, name: &str| ShareFile {
+            id: id.to_string(),
+            name: name.to_string(),
+            size: 0,
+        };
+        let mut share = share("nightly", None, DateTime::UNIX_EPOCH);
+        share.files = vec![
+            file("f1", "build.tar.gz"),
+            file("f2", "notes.txt"),
+            file("f3", "notes.txt"),
+        ];
+
+        assert_eq!(find_file(&share, "build.tar.gz").unwrap().id, "f1");
+        assert_eq!(find_file(&share, "f3").unwrap().id, "f3");
+        assert!(find_file(&share, "notes.txt").is_err());
+        assert!(find_file(&share, "missing").is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+
+        let table = format_table(
+            &["ID", "NAME"],
+            &[
+                vec!["abc".to_string(), "".to_string()],
+                vec!["a".to_string(), "Photos".to_string()],
+            ],
+        );
+        assert_eq!(table, "ID   NAME\nabc\na    Photos\n");
+    }
+}
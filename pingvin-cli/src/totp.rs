@@ -0,0 +1,73 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Context;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+const TIME_STEP: u64 = 30;
+const DIGITS: u32 = 6;
+
+/// Decode a base32 encoded secret as shown by authenticator apps
+pub fn decode_secret(secret: &str) -> anyhow::Result<Vec<u8>> {
+    let secret = secret
+        .chars()
+        .filter(|char| !char.is_whitespace() && *char != '=' && *char != '-')
+        .collect::<String>()
+        .to_ascii_uppercase();
+
+    data_encoding::BASE32_NOPAD
+        .decode(secret.as_bytes())
+        .context("invalid totp secret")
+}
+
+/// Generate the time-based one-time password (RFC 6238) for the given unix timestamp
+pub fn generate_code(secret: &[u8], timestamp: u64) -> String {
+    let counter = timestamp / TIME_STEP;
+
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("hmac accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let hash = mac.finalize().into_bytes();
+
+    /* dynamic truncation */
+    let offset = (hash[hash.len() - 1] & 0x0F) as usize;
+    let value = u32::from_be_bytes([
+        hash[offset] & 0x7F,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    format!(
+        "{:0width$}",
+        value % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    )
+}
+
+/// Generate the current code for the base32 encoded secret
+pub fn current_code(secret: &str) -> anyhow::Result<String> {
+    let secret = decode_secret(secret)?;
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    Ok(generate_code(&secret, timestamp))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_secret, generate_code};
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        let secret = b"12345678901234567890";
+        assert_eq!(generate_code(secret, 59), "287082");
+        assert_eq!(generate_code(secret, 1111111109), "081804");
+        assert_eq!(generate_code(secret, 1234567890), "005924");
+        assert_eq!(generate_code(secret, 20000000000), "353130");
+    }
+
+    #[test]
+    fn test_decode_secret() {
+        let secret = decode_secret("gezd gnbv gy3t qojq gezd gnbv gy3t qojq").unwrap();
+        assert_eq!(secret, b"12345678901234567890");
+        assert!(decode_secret("not base32!").is_err());
+    }
+}
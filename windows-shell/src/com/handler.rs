@@ -99,6 +99,9 @@ impl IExplorerCommand_Impl for PingvinExplorerCommandHandler_Impl {
             if let Some(args) = &config.pingvin_args {
                 command_args.extend(args.split(",").map(String::from));
             }
+            if let Some(profile) = &config.pingvin_profile {
+                command_args.extend_from_slice(&["--profile".to_string(), profile.clone()]);
+            }
             for file in files {
                 command_args.extend_from_slice(&["-f".to_string(), file.display().to_string()]);
             }
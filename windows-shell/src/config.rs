@@ -29,6 +29,10 @@ pub struct ShellConfig {
     /// Arguments must be split by ","
     pub pingvin_args: Option<String>,
 
+    /// Profile of the pingvin client config which should be used.
+    /// This avoids embedding credentials within the pingvin args.
+    pub pingvin_profile: Option<String>,
+
     /// Menu title to display for context menus
     pub menu_title: Option<String>,
 
@@ -39,6 +43,7 @@ pub struct ShellConfig {
 static CONFIG_INSTANCE: RwLock<ShellConfig> = RwLock::new(ShellConfig {
     pingvin_exe: None,
     pingvin_args: None,
+    pingvin_profile: None,
 
     menu_title: None,
     menu_icon: None,